@@ -1,11 +1,11 @@
 use std::collections::HashMap;
-use std::time::Duration;
 
-use bevy::{prelude::*, asset};
-use bevy_inspector_egui::egui::Key;
+use bevy::prelude::*;
+use bevy::asset::LoadState;
 use bevy_rapier2d::prelude::*;
 
 use crate::components::{GroundDetection, Player};
+use crate::state::GameState;
 
 #[derive(Debug)]
 pub struct AnimationPlugin;
@@ -13,9 +13,10 @@ pub struct AnimationPlugin;
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<AnimationResource>()
-            .add_systems(Update, animate_sprite)
-            .add_systems(Update, append_animation_for_player)
-            .add_systems(Update, change_player_animation);
+            .add_systems(Update, validate_animation_sheets.run_if(in_state(GameState::Loading)))
+            .add_systems(Update, animate_sprite.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, append_animation_for_player.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, change_player_animation.run_if(in_state(GameState::Playing)));
     }
 }
 
@@ -41,11 +42,8 @@ enum AnimationState {
     Roll,
     Dash,
     WallHang,
-    WallSlide,
     WallClimb,
-    WallClimbNoMovement,
     Death,
-    DeathNoMovement,
 }
 
 #[derive(Debug, Clone, Component)]
@@ -58,7 +56,7 @@ struct AnimationMeta {
 impl AnimationMeta {
     fn new(len: usize, fps: usize) -> AnimationMeta {
         AnimationMeta {
-            len: len,
+            len,
             frame_time: 1.0 / (fps as f32),
             crouch_elapsed: 0.0,
         }
@@ -66,7 +64,7 @@ impl AnimationMeta {
 }
 
 #[derive(Debug, Resource)]
-struct AnimationResource {
+pub struct AnimationResource {
     map: HashMap<AnimationState, (Handle<TextureAtlas>, AnimationMeta)>,
 }
 
@@ -77,6 +75,17 @@ impl AnimationResource {
     fn get(&self, state: AnimationState) -> Option<(Handle<TextureAtlas>, AnimationMeta)> {
         self.map.get(&state).cloned()
     }
+
+    /// Images behind every atlas, for the loading screen to wait on.
+    pub fn sheets<'a>(
+        &'a self,
+        texture_atlases: &'a Assets<TextureAtlas>
+    ) -> impl Iterator<Item = &'a Handle<Image>> {
+        self.map
+            .values()
+            .filter_map(|(atlas, _)| texture_atlases.get(atlas))
+            .map(|atlas| &atlas.texture)
+    }
 }
 
 impl FromWorld for AnimationResource {
@@ -95,8 +104,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Idle, texture_atlas.add(idle_atlas), AnimationMeta::new(9, 12));
-            //10
+            res.add(AnimationState::Idle, texture_atlas.add(idle_atlas), AnimationMeta::new(10, 12));
 
             let run_atlas = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Run.png"),
@@ -106,7 +114,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Run, texture_atlas.add(run_atlas), AnimationMeta::new(9, 12));
+            res.add(AnimationState::Run, texture_atlas.add(run_atlas), AnimationMeta::new(10, 12));
 
             let jump_atlas = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Jump.png"),
@@ -116,7 +124,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Jump, texture_atlas.add(jump_atlas), AnimationMeta::new(2, 12));
+            res.add(AnimationState::Jump, texture_atlas.add(jump_atlas), AnimationMeta::new(3, 12));
 
             let fall_atlas = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Fall.png"),
@@ -126,7 +134,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Fall, texture_atlas.add(fall_atlas), AnimationMeta::new(2, 1));
+            res.add(AnimationState::Fall, texture_atlas.add(fall_atlas), AnimationMeta::new(3, 12));
 
             let crouch_transition_atlas = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_CrouchTransition.png"),
                 Vec2::new(120.0, 80.0),
@@ -154,8 +162,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::CrouchWalk, texture_atlas.add(crouch_walk_atlas),AnimationMeta::new(7, 12));
-            //8
+            res.add(AnimationState::CrouchWalk, texture_atlas.add(crouch_walk_atlas),AnimationMeta::new(8, 12));
 
             let attack = TextureAtlas::from_grid(
             asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_AttackNoMovement.png"),
@@ -165,7 +172,7 @@ impl FromWorld for AnimationResource {
             None,
             None
             );
-            res.add(AnimationState::Attack, texture_atlas.add(attack),AnimationMeta::new(3,16));
+            res.add(AnimationState::Attack, texture_atlas.add(attack),AnimationMeta::new(4, 16));
 
             let attack_slide = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Attack.png"),
@@ -175,7 +182,7 @@ impl FromWorld for AnimationResource {
             None,
             None
             );
-            res.add(AnimationState::AttackSlide, texture_atlas.add(attack_slide), AnimationMeta::new(3, 16));
+            res.add(AnimationState::AttackSlide, texture_atlas.add(attack_slide), AnimationMeta::new(4, 16));
 
 
             let attack2 = TextureAtlas::from_grid(
@@ -186,7 +193,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Attack2, texture_atlas.add(attack2), AnimationMeta::new(5, 16));
+            res.add(AnimationState::Attack2, texture_atlas.add(attack2), AnimationMeta::new(6, 16));
 
             let attack2_slide = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Attack2.png"),
@@ -196,7 +203,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Attack2Slide, texture_atlas.add(attack2_slide), AnimationMeta::new(5, 16));
+            res.add(AnimationState::Attack2Slide, texture_atlas.add(attack2_slide), AnimationMeta::new(6, 16));
 
             let combo = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_AttackComboNoMovement.png"),
@@ -206,7 +213,7 @@ impl FromWorld for AnimationResource {
                 None,
                 None
             );
-            res.add(AnimationState::Combo, texture_atlas.add(combo), AnimationMeta::new(9, 16));
+            res.add(AnimationState::Combo, texture_atlas.add(combo), AnimationMeta::new(10, 16));
 
             let combo_slide = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_AttackCombo2hit.png"),
             Vec2::new(120.0, 80.0),
@@ -214,7 +221,7 @@ impl FromWorld for AnimationResource {
             1,
             None,
             None);
-            res.add(AnimationState::ComboSlide, texture_atlas.add(combo_slide), AnimationMeta::new(9, 16));
+            res.add(AnimationState::ComboSlide, texture_atlas.add(combo_slide), AnimationMeta::new(10, 16));
 
             let crouch_attack = TextureAtlas::from_grid(
                 asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_CrouchAttack.png"),
@@ -226,7 +233,7 @@ impl FromWorld for AnimationResource {
             None
             );
             res.add(AnimationState::CrouchAttack, texture_atlas.add(crouch_attack),
-             AnimationMeta::new(3, 16));
+             AnimationMeta::new(4, 16));
 
             let roll = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Roll.png"),
             Vec2::new(120.0, 80.0),
@@ -236,7 +243,7 @@ impl FromWorld for AnimationResource {
             None
             );
             res.add(AnimationState::Roll, texture_atlas.add(roll),
-            AnimationMeta::new(11, 12));
+            AnimationMeta::new(12, 12));
 
             let dash = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Dash.png"),
 
@@ -247,7 +254,7 @@ impl FromWorld for AnimationResource {
             None
             );
             res.add(AnimationState::Dash, texture_atlas.add(dash),
-            AnimationMeta::new(1,12));
+            AnimationMeta::new(2, 12));
 
             let slide = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_SlideFull.png"),
         Vec2::new(120.0, 80.0),
@@ -257,7 +264,7 @@ impl FromWorld for AnimationResource {
             None
             );
             res.add(AnimationState::Slide, texture_atlas.add(slide),
-        AnimationMeta::new(3,6));
+        AnimationMeta::new(4, 6));
 
             let slide_start = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_SlideTransitionStart.png"),
             Vec2::new(120.0, 80.0),
@@ -295,7 +302,7 @@ impl FromWorld for AnimationResource {
         None,
         None);
         res.add(AnimationState::WallClimb, texture_atlas.add(wall_climb),
-            AnimationMeta::new(6, 12));
+            AnimationMeta::new(7, 12));
 
             let death = TextureAtlas::from_grid(asset_server.load("Knight/Colour1/Outline/120x80_PNGSheets/_Death.png"),
             Vec2::new(120.0, 80.0),
@@ -304,13 +311,81 @@ impl FromWorld for AnimationResource {
         None,
         None);
         res.add(AnimationState::Death, texture_atlas.add(death),
-    AnimationMeta::new(9,12));
+    AnimationMeta::new(10, 12));
         });
 
         res
     }
 }
 
+const FRAME_WIDTH: f32 = 120.0;
+
+/// Checks every sheet against its atlas once they've all finished loading, so a wrong path,
+/// column count or frame count shows up in the log instead of as blank or skipped frames.
+fn validate_animation_sheets(
+    asset_server: Res<AssetServer>,
+    animations: Res<AnimationResource>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    images: Res<Assets<Image>>,
+    mut validated: Local<bool>
+) {
+    if *validated {
+        return;
+    }
+
+    let mut sheets: Vec<_> = animations.map
+        .iter()
+        .filter_map(|(state, (atlas, meta))| Some((state, texture_atlases.get(atlas)?, meta)))
+        .collect();
+    let finished = sheets.iter().all(|(_, atlas, _)| {
+        matches!(asset_server.get_load_state(&atlas.texture), LoadState::Loaded | LoadState::Failed)
+    });
+    if !finished {
+        return;
+    }
+    *validated = true;
+
+    sheets.sort_by_key(|(state, _, _)| format!("{:?}", state));
+
+    let mut problems = Vec::new();
+    for (state, atlas, meta) in &sheets {
+        let path = asset_server
+            .get_handle_path(&atlas.texture)
+            .map_or_else(|| "<unknown path>".to_string(), |path| path.path().display().to_string());
+        let Some(image) = images.get(&atlas.texture) else {
+            problems.push(format!("{:?}: {} is missing or could not be read", state, path));
+            continue;
+        };
+
+        let columns = atlas.textures.len();
+        let expected_width = (columns as f32) * FRAME_WIDTH;
+        if image.size().x != expected_width {
+            problems.push(
+                format!(
+                    "{:?}: {} is {}px wide, {} columns of {}px need {}px",
+                    state,
+                    path,
+                    image.size().x,
+                    columns,
+                    FRAME_WIDTH,
+                    expected_width
+                )
+            );
+        }
+        if meta.len != columns {
+            problems.push(
+                format!("{:?}: plays {} frames but {} has {} columns", state, meta.len, path, columns)
+            );
+        }
+    }
+
+    if problems.is_empty() {
+        info!("Validated {} animation sheets", sheets.len());
+    } else {
+        error!("Animation sheet problems:\n  {}", problems.join("\n  "));
+    }
+}
+
 #[derive(Component)]
 struct FrameTime(pub f32);
 
@@ -370,8 +445,8 @@ fn append_animation_for_player(
     commands.entity(entity).insert(PhoxAnimationBundle::new(animation));
 }
 
-fn detect_wall() {}
-
+// The slide branch below is left as-is until the slide animations are reworked
+#[allow(clippy::type_complexity, non_snake_case, unused_assignments)]
 fn change_player_animation(
     input: Res<Input<KeyCode>>,
     mut player: Query<
@@ -407,9 +482,12 @@ fn change_player_animation(
 
     let mut isSliding = false;
 
-    if velocity.linvel.y > 0.01 {
+    // Walking along a slope moves the player vertically without being airborne
+    let on_slope = ground_detection.on_ground && ground_detection.slope.is_some();
+
+    if velocity.linvel.y > 0.01 && !on_slope {
         set = AnimationState::Jump
-    } else if velocity.linvel.y < -0.01 {
+    } else if velocity.linvel.y < -0.01 && !on_slope {
         set =  AnimationState::Fall
     }
     else if input.pressed(KeyCode::S) && input.pressed(KeyCode::J){
@@ -0,0 +1,307 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{ Deserialize, Serialize };
+use std::f32::consts::PI;
+
+use crate::collisions::MergedCells;
+use crate::components::{
+    Checkpoint,
+    GroundDetection,
+    Hazard,
+    Health,
+    Player,
+    CHECKPOINT_ACTIVE_COLOR,
+    CHECKPOINT_COLOR,
+    HAZARD_COLOR,
+};
+use crate::effects::spawn_particle_burst;
+use crate::save::WaitingForLevel;
+use crate::state::GameState;
+use crate::systems::RestartWorld;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnPoint>()
+            .add_systems(Update, activate_checkpoints.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, animate_checkpoint_activation.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, restore_active_checkpoint)
+            .add_systems(Update, kill_fallen_player.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, kill_on_hazards.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, show_hazards)
+            .add_systems(Update, respawn_player.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, reset_respawn_point);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointLocation {
+    pub checkpoint_iid: String,
+    pub level_iid: String,
+    /// Where the player's feet go.
+    pub feet: Vec2,
+}
+
+/// Last checkpoint the player touched, without one dying is game over.
+#[derive(Resource, Default)]
+pub struct RespawnPoint {
+    pub checkpoint: Option<CheckpointLocation>,
+}
+
+#[derive(Component)]
+pub struct CheckpointActivation {
+    pub timer: Timer,
+}
+
+/// How far below the lowest spawned level the player has to fall before they count as dead.
+const KILL_MARGIN: f32 = 64.0;
+
+#[allow(clippy::type_complexity)]
+fn activate_checkpoints(
+    mut commands: Commands,
+    player_query: Query<(&GlobalTransform, &Collider), With<Player>>,
+    mut checkpoint_query: Query<(Entity, &mut Checkpoint, &mut Sprite, &EntityIid, &GlobalTransform, &Parent)>,
+    layer_query: Query<&Parent, With<LayerMetadata>>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    mut respawn_point: ResMut<RespawnPoint>
+) {
+    let Ok((player_transform, player_collider)) = player_query.get_single() else {
+        return;
+    };
+    let player_translation = player_transform.translation().truncate();
+    let player_half_size = player_collider
+        .as_cuboid()
+        .map(|cuboid| cuboid.half_extents())
+        .unwrap_or_default();
+
+    let touched = checkpoint_query.iter().find_map(|(entity, checkpoint, _, _, transform, _)| {
+        let inside = rects_overlap(
+            player_translation,
+            player_half_size,
+            transform.translation().truncate(),
+            checkpoint.half_size
+        );
+        (inside && !checkpoint.active).then_some(entity)
+    });
+    let Some(touched) = touched else {
+        return;
+    };
+
+    for (entity, mut checkpoint, mut sprite, iid, transform, parent) in &mut checkpoint_query {
+        if entity != touched {
+            if checkpoint.active {
+                checkpoint.active = false;
+                sprite.color = CHECKPOINT_COLOR;
+            }
+            continue;
+        }
+
+        let Some(level) = layer_query
+            .get(parent.get())
+            .ok()
+            .and_then(|layer_parent| level_query.get(layer_parent.get()).ok())
+            .and_then(|level_handle| ldtk_levels.get(level_handle)) else {
+            continue;
+        };
+
+        checkpoint.active = true;
+        sprite.color = CHECKPOINT_ACTIVE_COLOR;
+        commands.entity(entity).insert(CheckpointActivation {
+            timer: Timer::from_seconds(0.4, TimerMode::Once),
+        });
+        spawn_particle_burst(&mut commands, transform.translation(), CHECKPOINT_ACTIVE_COLOR, 10);
+
+        respawn_point.checkpoint = Some(CheckpointLocation {
+            checkpoint_iid: iid.as_str().to_string(),
+            level_iid: level.level.iid.clone(),
+            feet: transform.translation().truncate() - Vec2::new(0.0, checkpoint.half_size.y),
+        });
+    }
+}
+
+/// Whether two centered rectangles touch, edges included.
+fn rects_overlap(center_a: Vec2, half_size_a: Vec2, center_b: Vec2, half_size_b: Vec2) -> bool {
+    let offset = (center_a - center_b).abs();
+    let reach = half_size_a + half_size_b;
+    offset.x <= reach.x && offset.y <= reach.y
+}
+
+/// Swells up and settles back down once.
+fn animate_checkpoint_activation(
+    mut commands: Commands,
+    mut activation_query: Query<(Entity, &mut CheckpointActivation, &mut Transform)>,
+    time: Res<Time>
+) {
+    for (entity, mut activation, mut transform) in &mut activation_query {
+        if activation.timer.tick(time.delta()).finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<CheckpointActivation>();
+            continue;
+        }
+
+        let pulse = 1.0 + 0.4 * (activation.timer.percent() * PI).sin();
+        transform.scale = Vec3::new(pulse, pulse, 1.0);
+    }
+}
+
+/// Levels lose their state when they get respawned, light the active checkpoint back up.
+fn restore_active_checkpoint(
+    respawn_point: Res<RespawnPoint>,
+    mut checkpoint_query: Query<(&mut Checkpoint, &mut Sprite, &EntityIid), Added<Checkpoint>>
+) {
+    let Some(location) = &respawn_point.checkpoint else {
+        return;
+    };
+
+    for (mut checkpoint, mut sprite, iid) in &mut checkpoint_query {
+        if iid.as_str() == location.checkpoint_iid {
+            checkpoint.active = true;
+            sprite.color = CHECKPOINT_ACTIVE_COLOR;
+        }
+    }
+}
+
+/// Not while a loaded player is still waiting for their level, which may be further down.
+#[allow(clippy::type_complexity)]
+fn kill_fallen_player(
+    mut player_query: Query<(&Transform, &mut Health), (With<Player>, Without<WaitingForLevel>)>,
+    level_query: Query<&Transform, (With<Handle<LdtkLevel>>, Without<Player>)>
+) {
+    let Some(lowest) = level_query
+        .iter()
+        .map(|level_transform| level_transform.translation.y)
+        .reduce(f32::min) else {
+        return;
+    };
+
+    for (transform, mut health) in &mut player_query {
+        if transform.translation.y < lowest - KILL_MARGIN && health.current > 0 {
+            health.current = 0;
+        }
+    }
+}
+
+fn kill_on_hazards(
+    mut collisions: EventReader<CollisionEvent>,
+    hazard_query: Query<(), With<MergedCells<Hazard>>>,
+    mut player_query: Query<&mut Health, With<Player>>
+) {
+    for collision_event in collisions.iter() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        for (hazard, other) in [(*e1, *e2), (*e2, *e1)] {
+            if !hazard_query.contains(hazard) {
+                continue;
+            }
+            if let Ok(mut health) = player_query.get_mut(other) {
+                health.current = 0;
+            }
+        }
+    }
+}
+
+fn show_hazards(
+    mut commands: Commands,
+    hazard_query: Query<(Entity, &Parent), Added<Hazard>>,
+    layer_query: Query<&LayerMetadata>
+) {
+    for (entity, parent) in &hazard_query {
+        let Ok(layer) = layer_query.get(parent.get()) else {
+            continue;
+        };
+
+        commands.entity(entity).insert((
+            Sprite {
+                color: HAZARD_COLOR,
+                custom_size: Some(Vec2::splat(layer.grid_size as f32)),
+                ..default()
+            },
+            Handle::<Image>::default(),
+        ));
+    }
+}
+
+/// Puts a dead player back on their feet at the respawn point, selecting its level so it
+/// gets spawned if the player died somewhere else. Without a checkpoint it's game over.
+#[allow(clippy::type_complexity)]
+fn respawn_player(
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut GravityScale,
+            &mut Health,
+            &mut GroundDetection,
+            &Collider,
+        ),
+        With<Player>
+    >,
+    respawn_point: Res<RespawnPoint>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    for (mut transform, mut velocity, mut gravity, mut health, mut ground_detection, collider) in &mut player_query {
+        if health.current > 0 {
+            continue;
+        }
+
+        velocity.linvel = Vec2::ZERO;
+        velocity.angvel = 0.0;
+        *gravity = GravityScale(1.0);
+        health.current = health.max;
+        *ground_detection = GroundDetection::default();
+
+        let Some(location) = &respawn_point.checkpoint else {
+            next_state.set(GameState::GameOver);
+            continue;
+        };
+
+        let half_height = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents().y)
+            .unwrap_or_default();
+        transform.translation.x = location.feet.x;
+        transform.translation.y = location.feet.y + half_height;
+
+        let selection = LevelSelection::Iid(location.level_iid.clone());
+        if *level_selection != selection {
+            *level_selection = selection;
+        }
+    }
+}
+
+fn reset_respawn_point(mut restarts: EventReader<RestartWorld>, mut respawn_point: ResMut<RespawnPoint>) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    respawn_point.checkpoint = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sizes from Level_0: a 16x32 checkpoint and the player's 12x80 collider, both on the floor at y = 0
+    const CHECKPOINT_CENTER: Vec2 = Vec2::new(536.0, 16.0);
+    const CHECKPOINT_HALF_SIZE: Vec2 = Vec2::new(8.0, 16.0);
+    const PLAYER_HALF_SIZE: Vec2 = Vec2::new(6.0, 40.0);
+
+    #[test]
+    fn player_standing_next_to_checkpoint_touches_it() {
+        let player_center = Vec2::new(CHECKPOINT_CENTER.x - 12.0, PLAYER_HALF_SIZE.y);
+        assert!(rects_overlap(player_center, PLAYER_HALF_SIZE, CHECKPOINT_CENTER, CHECKPOINT_HALF_SIZE));
+    }
+
+    #[test]
+    fn player_out_of_reach_does_not_touch_checkpoint() {
+        let player_center = Vec2::new(CHECKPOINT_CENTER.x - 20.0, PLAYER_HALF_SIZE.y);
+        assert!(!rects_overlap(player_center, PLAYER_HALF_SIZE, CHECKPOINT_CENTER, CHECKPOINT_HALF_SIZE));
+    }
+}
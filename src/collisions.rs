@@ -1,140 +1,552 @@
 use crate::components::*;
-use bevy::{ prelude::*, render::render_resource::Texture };
+use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
-use bevy_inspector_egui::egui::Key;
 
-use std::{ collections::{ HashMap, HashSet }, f32::consts::E };
+use bevy::utils::get_short_name;
+use std::{ any::type_name, collections::{ HashMap, HashSet }, marker::PhantomData };
 
+use bevy::ecs::system::SystemParam;
+use bevy::sprite::Mesh2dHandle;
 use bevy_rapier2d::prelude::*;
 
-pub fn spawn_wall_collision(
+/// How the merged colliders spawned for an IntGrid value behave.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ColliderKind {
+    Solid,
+    /// Blocks nothing, only sends collision events.
+    Sensor,
+    /// Solid when landed on from above, can be jumped through from below.
+    OneWay,
+}
+
+#[derive(Clone, Debug)]
+pub struct MergedColliderSettings {
+    /// Identifier of the IntGrid layer to take the cells from, `None` for whichever layer
+    /// the value is painted on.
+    pub layer: Option<&'static str>,
+    pub kind: ColliderKind,
+    pub friction: f32,
+}
+
+/// Marker component for IntGrid cells that get merged into colliders,
+/// add `spawn_merged_collision::<T>` to the app for each implementor. Cells are merged
+/// per IntGrid layer.
+pub trait MergedCollider: Component {
+    fn settings() -> MergedColliderSettings;
+}
+
+/// Bool level field switching a level from rectangle colliders to one polyline
+/// outline per connected region, which has no internal edges to catch on.
+pub const OUTLINE_COLLIDERS_FIELD: &str = "Outline_colliders";
+
+/// Added to the colliders spawned for `ColliderKind::OneWay` cells.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct OneWayPlatform;
+
+/// Cells covered by one of the colliders spawned by `spawn_merged_collision::<T>`.
+#[derive(Component)]
+pub struct MergedCells<T: MergedCollider> {
+    pub cells: HashSet<GridCoords>,
+    layer: Entity,
+    marker: PhantomData<T>,
+}
+
+/// Merges the cells of each level into colliders and keeps them up to date as cells are
+/// added or removed. Only colliders on or next to a changed cell are despawned, the cells
+/// they covered are merged again together with the new ones. Changes to levels whose asset
+/// isn't loaded yet are kept for the next frame.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn spawn_merged_collision<T: MergedCollider>(
     mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<Wall>>,
-    parent_query: Query<&Parent, Without<Wall>>,
-    level_query: Query<(Entity, &Handle<LdtkLevel>)>,
+    mut known_cells: Local<HashMap<Entity, (Entity, GridCoords)>>,
+    mut pending_changes: Local<HashMap<Entity, HashSet<GridCoords>>>,
+    cell_query: Query<(Entity, &GridCoords, &Parent), Added<T>>,
+    mut removed_cells: RemovedComponents<T>,
+    layer_query: Query<(&Parent, &LayerMetadata), Without<T>>,
+    collider_query: Query<(Entity, &MergedCells<T>)>,
+    level_query: Query<&Handle<LdtkLevel>>,
     levels: Res<Assets<LdtkLevel>>
 ) {
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
-    }
+    let settings = T::settings();
+
+    let mut layer_to_changed_cells: HashMap<Entity, HashSet<GridCoords>> = std::mem::take(&mut *pending_changes);
+
+    cell_query.for_each(|(cell_entity, &grid_coords, parent)| {
+        let Ok((_, layer)) = layer_query.get(parent.get()) else {
+            return;
+        };
+        if settings.layer.is_some_and(|identifier| layer.identifier != identifier) {
+            return;
+        }
+
+        known_cells.insert(cell_entity, (parent.get(), grid_coords));
+        layer_to_changed_cells.entry(parent.get()).or_default().insert(grid_coords);
+    });
 
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
+    for cell_entity in removed_cells.iter() {
+        if let Some((layer_entity, grid_coords)) = known_cells.remove(&cell_entity) {
+            layer_to_changed_cells.entry(layer_entity).or_default().insert(grid_coords);
+        }
     }
 
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
+    for (layer_entity, changed_cells) in layer_to_changed_cells {
+        // Despawned levels take their layers and colliders with them
+        let Ok((level_entity, layer)) = layer_query.get(layer_entity) else {
+            continue;
+        };
+        let level_entity = level_entity.get();
+        let Ok(level_handle) = level_query.get(level_entity) else {
+            continue;
+        };
+        // Reloads can spawn the level before its asset is ready, try again next frame
+        let Some(level) = levels.get(level_handle) else {
+            pending_changes.insert(layer_entity, changed_cells);
+            continue;
+        };
+        let (width, height, grid_size) = (layer.c_wid, layer.c_hei, layer.grid_size);
+
+        let level_cells: HashSet<GridCoords> = known_cells
+            .values()
+            .filter(|(cell_layer, _)| *cell_layer == layer_entity)
+            .map(|(_, grid_coords)| *grid_coords)
+            .collect();
+
+        // Include neighbours so new cells get merged with the colliders next to them
+        let affected_cells: HashSet<GridCoords> = changed_cells
+            .iter()
+            .flat_map(|cell| {
+                [
+                    *cell,
+                    GridCoords::new(cell.x - 1, cell.y),
+                    GridCoords::new(cell.x + 1, cell.y),
+                    GridCoords::new(cell.x, cell.y - 1),
+                    GridCoords::new(cell.x, cell.y + 1),
+                ]
+            })
+            .collect();
+
+        let mut rebuild_cells: HashSet<GridCoords> = changed_cells
+            .intersection(&level_cells)
+            .copied()
+            .collect();
 
-    wall_query.for_each(|(&grid_coords, parent)| {
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_wall_locations.entry(grandparent.get()).or_default().insert(grid_coords);
+        for (collider_entity, merged) in &collider_query {
+            if merged.layer == layer_entity && !merged.cells.is_disjoint(&affected_cells) {
+                rebuild_cells.extend(merged.cells.intersection(&level_cells));
+                commands.entity(collider_entity).despawn_recursive();
+            }
         }
-    });
 
-    if !wall_query.is_empty() {
-        level_query.for_each(|(level_entity, level_handle)| {
-            if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let level = levels.get(level_handle).expect("Level should be loaded by this point");
-
-                let LayerInstance {
-                    c_wid: width,
-                    c_hei: height,
-                    grid_size,
-                    ..
-                } = level.level.layer_instances.clone().expect("Level asset should have layers")[0];
-
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&(GridCoords { x, y }))) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => {
-                                plate_start = Some(x);
-                            }
-                            _ => (),
-                        }
-                    }
+        if rebuild_cells.is_empty() {
+            continue;
+        }
 
-                    plate_stack.push(row_plates);
+        let outline_colliders = level.level
+            .get_bool_field(OUTLINE_COLLIDERS_FIELD)
+            .copied()
+            .unwrap_or(false);
+        let outlines = if outline_colliders {
+            match trace_outlines(&rebuild_cells) {
+                Ok(outlines) => Some(outlines),
+                Err(error) => {
+                    warn!("{}: {}, using rectangle colliders instead", level.level.identifier, error);
+                    None
                 }
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row: Vec<Plate> = Vec::new();
-                let mut wall_rects: Vec<Rect> = Vec::new();
-                plate_stack.push(Vec::new());
-
-                for (y, current_row) in plate_stack.into_iter().enumerate() {
-                    for prev_plate in &prev_row {
-                        if !current_row.contains(prev_plate) {
-                            if let Some(rect) = rect_builder.remove(prev_plate) {
-                                wall_rects.push(rect);
-                            }
-                        }
-                    }
-                    for plate in &current_row {
-                        rect_builder
-                            .entry(plate.clone())
-                            .and_modify(|e| {
-                                e.top += 1;
-                            })
-                            .or_insert(Rect {
-                                bottom: y as i32,
-                                top: y as i32,
-                                left: plate.left,
-                                right: plate.right,
+            }
+        } else {
+            None
+        };
+        let outlined = outlines.is_some();
+
+        // Collider, where it goes, the cells it covers and any filling to spawn inside it
+        let colliders: Vec<(Collider, Transform, HashSet<GridCoords>, Vec<(Collider, Transform)>)> = match outlines {
+            Some(outlines) =>
+                outlines
+                    .into_iter()
+                    .map(|(cells, loops)| {
+                        // One-way platforms have to stay passable from below
+                        let fill = if settings.kind == ColliderKind::Solid {
+                            outline_fill(&cells, width, height, grid_size)
+                        } else {
+                            Vec::new()
+                        };
+                        (outline_collider(loops, grid_size), Transform::default(), cells, fill)
+                    })
+                    .collect(),
+            None =>
+                decompose_into_rects(&rebuild_cells, width, height)
+                    .into_iter()
+                    .map(|cell_rect| {
+                        let (collider, transform) = cell_rect.collider(grid_size as f32, |_| 0.0);
+                        (collider, transform, cell_rect.cells(), Vec::new())
+                    })
+                    .collect(),
+        };
+
+        info!(
+            "{}: merged {} {} cells into {} {} colliders",
+            level.level.identifier,
+            rebuild_cells.len(),
+            get_short_name(type_name::<T>()),
+            colliders.len(),
+            if outlined { "outline" } else { "rectangle" }
+        );
+
+        commands.entity(level_entity).with_children(|level| {
+            for (shape, transform, cells, fill) in colliders {
+                let mut collider = level.spawn_empty();
+                collider
+                    .insert(shape)
+                    .insert(transform)
+                    .insert(GlobalTransform::default())
+                    .insert(MergedCells::<T> {
+                        cells,
+                        layer: layer_entity,
+                        marker: PhantomData,
+                    });
+
+                match settings.kind {
+                    ColliderKind::Solid => {
+                        collider
+                            .insert(RigidBody::Fixed)
+                            .insert(Friction::new(settings.friction))
+                            .with_children(|filling| {
+                                for (shape, transform) in fill {
+                                    filling.spawn((shape, TransformBundle::from_transform(transform)));
+                                }
                             });
                     }
-                    prev_row = current_row;
-                }
-
-                commands.entity(level_entity).with_children(|level| {
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn_empty()
-                            .insert(
-                                Collider::cuboid(
-                                    (((wall_rect.right as f32) - (wall_rect.left as f32) + 1.0) *
-                                        (grid_size as f32)) /
-                                        2.0,
-                                    (((wall_rect.top as f32) - (wall_rect.bottom as f32) + 1.0) *
-                                        (grid_size as f32)) /
-                                        2.0
-                                )
-                            )
+                    ColliderKind::Sensor => {
+                        collider.insert(Sensor).insert(ActiveEvents::COLLISION_EVENTS);
+                    }
+                    ColliderKind::OneWay => {
+                        collider
                             .insert(RigidBody::Fixed)
-                            .insert(Friction::new(1.0))
-                            .insert(
-                                Transform::from_xyz(
-                                    (((wall_rect.left + wall_rect.right + 1) as f32) *
-                                        (grid_size as f32)) /
-                                        2.0,
-                                    (((wall_rect.bottom + wall_rect.top + 1) as f32) *
-                                        (grid_size as f32)) /
-                                        2.0,
-                                    0.0
-                                )
-                            )
-                            .insert(GlobalTransform::default());
+                            .insert(Friction::new(settings.friction))
+                            .insert(OneWayPlatform)
+                            .insert(ActiveHooks::MODIFY_SOLVER_CONTACTS);
                     }
-                });
+                }
             }
         });
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Side {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+/// Inclusive cell bounds of a merged collider.
+struct Rect {
+    left: i32,
+    right: i32,
+    top: i32,
+    bottom: i32,
+}
+
+impl Rect {
+    fn cells(&self) -> HashSet<GridCoords> {
+        (self.left..=self.right)
+            .flat_map(|x| (self.bottom..=self.top).map(move |y| GridCoords::new(x, y)))
+            .collect()
+    }
+
+    /// Cuboid over the rectangle in level-local pixels, shrunk by `inset(side)` on each side.
+    fn collider(&self, grid_size: f32, inset: impl Fn(Side) -> f32) -> (Collider, Transform) {
+        let min = Vec2::new(
+            (self.left as f32) * grid_size + inset(Side::Left),
+            (self.bottom as f32) * grid_size + inset(Side::Bottom)
+        );
+        let max = Vec2::new(
+            ((self.right + 1) as f32) * grid_size - inset(Side::Right),
+            ((self.top + 1) as f32) * grid_size - inset(Side::Top)
+        );
+        let half_size = (max - min) / 2.0;
+
+        (Collider::cuboid(half_size.x, half_size.y), Transform::from_translation(((min + max) / 2.0).extend(0.0)))
+    }
+
+    /// Cells just past one side, corners included.
+    fn beyond(&self, side: Side) -> Vec<GridCoords> {
+        match side {
+            Side::Left => (self.bottom - 1..=self.top + 1).map(|y| GridCoords::new(self.left - 1, y)).collect(),
+            Side::Right => (self.bottom - 1..=self.top + 1).map(|y| GridCoords::new(self.right + 1, y)).collect(),
+            Side::Bottom => (self.left - 1..=self.right + 1).map(|x| GridCoords::new(x, self.bottom - 1)).collect(),
+            Side::Top => (self.left - 1..=self.right + 1).map(|x| GridCoords::new(x, self.top + 1)).collect(),
+        }
+    }
+
+    fn transposed(self) -> Rect {
+        Rect {
+            left: self.bottom,
+            right: self.top,
+            top: self.right,
+            bottom: self.left,
+        }
+    }
+}
+
+/// Covers the cells with non-overlapping rectangles, trying both row-first and
+/// column-first growth and keeping whichever needs fewer colliders.
+fn decompose_into_rects(cells: &HashSet<GridCoords>, width: i32, height: i32) -> Vec<Rect> {
+    let rows_first = grow_rects(cells, width, height, |x, y| GridCoords { x, y });
+    let columns_first: Vec<Rect> = grow_rects(cells, height, width, |y, x| GridCoords { x, y })
+        .into_iter()
+        .map(Rect::transposed)
+        .collect();
+
+    if columns_first.len() < rows_first.len() {
+        columns_first
+    } else {
+        rows_first
+    }
+}
+
+/// Greedy decomposition: from the lowest, leftmost uncovered cell, grow a rectangle
+/// as wide as possible, then as tall as possible at that width.
+/// `coords` maps the scan axes to grid coordinates so the same code can scan columns.
+fn grow_rects(
+    cells: &HashSet<GridCoords>,
+    width: i32,
+    height: i32,
+    coords: impl Fn(i32, i32) -> GridCoords
+) -> Vec<Rect> {
+    let mut covered: HashSet<GridCoords> = HashSet::new();
+    let mut rects: Vec<Rect> = Vec::new();
+
+    let free = |x: i32, y: i32, covered: &HashSet<GridCoords>| {
+        let grid_coords = coords(x, y);
+        cells.contains(&grid_coords) && !covered.contains(&grid_coords)
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            if !free(x, y, &covered) {
+                continue;
+            }
+
+            let mut right = x;
+            while right + 1 < width && free(right + 1, y, &covered) {
+                right += 1;
+            }
+
+            let mut top = y;
+            while top + 1 < height && (x..=right).all(|i| free(i, top + 1, &covered)) {
+                top += 1;
+            }
+
+            for i in x..=right {
+                for j in y..=top {
+                    covered.insert(coords(i, j));
+                }
+            }
+
+            rects.push(Rect {
+                left: x,
+                right,
+                top,
+                bottom: y,
+            });
+        }
+    }
+
+    rects
+}
+
+/// Traces the boundary of every 4-connected region of cells into closed loops of
+/// cell corner coordinates, wound counter-clockwise around the solid side.
+/// Returns the cells of each region along with its loops, regions with holes have more than one.
+/// Regions are traced in the same order every time, whatever order `cells` iterates in.
+#[allow(clippy::type_complexity)]
+fn trace_outlines(cells: &HashSet<GridCoords>) -> Result<Vec<(HashSet<GridCoords>, Vec<Vec<IVec2>>)>, String> {
+    const NEIGHBOURS: [IVec2; 4] = [IVec2::NEG_Y, IVec2::X, IVec2::Y, IVec2::NEG_X];
+
+    let mut visited: HashSet<GridCoords> = HashSet::new();
+    let mut regions: Vec<(HashSet<GridCoords>, Vec<Vec<IVec2>>)> = Vec::new();
+
+    let mut seeds: Vec<GridCoords> = cells.iter().copied().collect();
+    seeds.sort_by_key(|cell| (cell.y, cell.x));
+
+    for seed in seeds {
+        if !visited.insert(seed) {
+            continue;
+        }
+
+        // Each exposed cell side becomes a directed edge, keyed by its start corner
+        let mut edges: HashMap<IVec2, Vec<IVec2>> = HashMap::new();
+        let mut region_cells: HashSet<GridCoords> = HashSet::new();
+        let mut stack = vec![seed];
+
+        while let Some(cell) = stack.pop() {
+            region_cells.insert(cell);
+            let corner = IVec2::new(cell.x, cell.y);
+            let corners = [
+                corner,
+                corner + IVec2::X,
+                corner + IVec2::ONE,
+                corner + IVec2::Y,
+            ];
+
+            for (side, offset) in NEIGHBOURS.iter().enumerate() {
+                let neighbour = GridCoords::new(cell.x + offset.x, cell.y + offset.y);
+                if cells.contains(&neighbour) {
+                    if visited.insert(neighbour) {
+                        stack.push(neighbour);
+                    }
+                } else {
+                    edges
+                        .entry(corners[side])
+                        .or_default()
+                        .push(corners[(side + 1) % 4]);
+                }
+            }
+        }
+
+        let mut loops: Vec<Vec<IVec2>> = Vec::new();
+        while let Some(&start) = edges.keys().min_by_key(|corner| (corner.y, corner.x)) {
+            let mut outline = vec![start];
+            let mut current = start;
+            let mut direction: Option<IVec2> = None;
+
+            loop {
+                let Some(ends) = edges.get_mut(&current) else {
+                    return Err(format!("outline edges don't close at corner {}", current));
+                };
+
+                // Where two cells only touch at this corner there are two ways on. Turning right
+                // keeps following the same empty cell, so each loop only passes the corner once
+                let turn = |end: &IVec2| {
+                    match direction {
+                        Some(direction) if *end - current == -direction.perp() => 2,
+                        Some(direction) if *end - current == direction => 1,
+                        _ => 0,
+                    }
+                };
+                let index = (0..ends.len()).max_by_key(|&i| turn(&ends[i])).unwrap_or_default();
+                let next = ends.swap_remove(index);
+                if ends.is_empty() {
+                    edges.remove(&current);
+                }
+                if next == start {
+                    break;
+                }
+                outline.push(next);
+                direction = Some(next - current);
+                current = next;
+            }
+
+            loops.push(remove_collinear_points(outline));
+        }
+
+        regions.push((region_cells, loops));
+    }
+
+    Ok(regions)
+}
+
+fn remove_collinear_points(outline: Vec<IVec2>) -> Vec<IVec2> {
+    let len = outline.len();
+    (0..len)
+        .filter(|&i| {
+            let prev = outline[(i + len - 1) % len];
+            let next = outline[(i + 1) % len];
+            (outline[i] - prev).signum() != (next - outline[i]).signum()
+        })
+        .map(|i| outline[i])
+        .collect()
+}
+
+/// How far into a cell the filling of an outline collider stays from the outline, so nothing
+/// resting on the outline ever touches it.
+const OUTLINE_FILL_INSET: f32 = 0.25;
+
+/// Rectangles filling an outlined region, kept just inside the outline. The outline alone is
+/// hollow, bodies fast enough to pass through it in one step hit these instead of ending up
+/// stuck inside it.
+fn outline_fill(cells: &HashSet<GridCoords>, width: i32, height: i32, grid_size: i32) -> Vec<(Collider, Transform)> {
+    let grid_size = grid_size as f32;
+
+    decompose_into_rects(cells, width, height)
+        .into_iter()
+        .map(|rect| {
+            rect.collider(grid_size, |side| {
+                let exposed = rect
+                    .beyond(side)
+                    .iter()
+                    .any(|cell| !cells.contains(cell));
+                if exposed { OUTLINE_FILL_INSET * grid_size } else { 0.0 }
+            })
+        })
+        .collect()
+}
+
+/// Closed polyline through all the loops of one region, in level-local pixels.
+fn outline_collider(loops: Vec<Vec<IVec2>>, grid_size: i32) -> Collider {
+    let mut vertices: Vec<Vect> = Vec::new();
+    let mut indices: Vec<[u32; 2]> = Vec::new();
+
+    for outline in loops {
+        let base = vertices.len() as u32;
+        let len = outline.len() as u32;
+        vertices.extend(outline.iter().map(|corner| (*corner * grid_size).as_vec2()));
+        indices.extend((0..len).map(|i| [base + i, base + ((i + 1) % len)]));
+    }
+
+    Collider::polyline(vertices, Some(indices))
+}
+
+pub fn show_slopes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    slope_query: Query<(Entity, &Slope, &Parent), Added<Slope>>,
+    layer_query: Query<&LayerMetadata>
+) {
+    for (entity, slope, parent) in &slope_query {
+        let Ok(layer) = layer_query.get(parent.get()) else {
+            continue;
+        };
+
+        commands.entity(entity).insert((
+            Mesh2dHandle(meshes.add(slope.mesh(layer.grid_size as f32))),
+            materials.add(ColorMaterial::from(SLOPE_COLOR)),
+        ));
+    }
+}
+
+pub fn spawn_slope_collision(
+    mut commands: Commands,
+    slope_query: Query<(&GridCoords, &Slope, &Parent), Added<Slope>>,
+    layer_query: Query<(&Parent, &LayerMetadata)>
+) {
+    slope_query.for_each(|(grid_coords, slope, parent)| {
+        if let Ok((grandparent, layer)) = layer_query.get(parent.get()) {
+            let grid_size = layer.grid_size as f32;
+
+            commands.entity(grandparent.get()).with_children(|level| {
+                level
+                    .spawn_empty()
+                    .insert(slope.collider(grid_size))
+                    .insert(*slope)
+                    .insert(RigidBody::Fixed)
+                    .insert(Friction::new(1.0))
+                    .insert(
+                        Transform::from_xyz(
+                            ((grid_coords.x as f32) + 0.5) * grid_size,
+                            ((grid_coords.y as f32) + 0.5) * grid_size,
+                            0.0
+                        )
+                    )
+                    .insert(GlobalTransform::default());
+            });
+        }
+    });
+}
+
 pub fn spawn_ground_sensor(
     mut commands: Commands,
     detect_ground_for: Query<(Entity, &Collider), Added<GroundDetection>>
@@ -143,9 +555,13 @@ pub fn spawn_ground_sensor(
         if let Some(cuboid) = shape.as_cuboid() {
             let Vec2 { x: half_extents_x, y: half_extents_y } = cuboid.half_extents();
 
-            let detector_shape = Collider::cuboid(half_extents_x / 2.0, 2.0);
+            // Reach a little below the feet so the sensor still touches a 45° slope
+            // while the collider is resting on one of its corners.
+            let slope_reach = half_extents_x / 4.0;
 
-            let sensor_translation = Vec3::new(0.0, -half_extents_y, 0.0);
+            let detector_shape = Collider::cuboid(half_extents_x / 2.0, 2.0 + slope_reach);
+
+            let sensor_translation = Vec3::new(0.0, -half_extents_y - slope_reach, 0.0);
 
             commands.entity(entity).with_children(|builder| {
                 builder
@@ -199,11 +615,111 @@ pub fn ground_detection(
 
 pub fn update_on_ground(
     mut ground_detectors: Query<&mut GroundDetection>,
-    ground_sensors: Query<&GroundSensor, Changed<GroundSensor>>
+    ground_sensors: Query<&GroundSensor, Changed<GroundSensor>>,
+    slopes: Query<&Slope>
 ) {
     for sensor in &ground_sensors {
         if let Ok(mut ground_detection) = ground_detectors.get_mut(sensor.ground_detection_entity) {
             ground_detection.on_ground = !sensor.intersecting_ground_entities.is_empty();
+
+            let mut slope = None;
+            let mut on_flat_ground = false;
+            for entity in &sensor.intersecting_ground_entities {
+                match slopes.get(*entity) {
+                    Ok(touching) => {
+                        slope = Some(*touching);
+                    }
+                    Err(_) => {
+                        on_flat_ground = true;
+                    }
+                }
+            }
+            ground_detection.slope = if on_flat_ground { None } else { slope };
+        }
+    }
+}
+
+/// Physics hooks letting bodies pass through `OneWayPlatform` colliders from below.
+#[derive(SystemParam)]
+pub struct OneWayPlatformHooks<'w, 's> {
+    platforms: Query<'w, 's, &'static OneWayPlatform>,
+}
+
+impl BevyPhysicsHooks for OneWayPlatformHooks<'_, '_> {
+    fn modify_solver_contacts(&self, context: ContactModificationContextView) {
+        // The allowed normal points out of the first collider, towards the second one
+        let allowed_normal = if self.platforms.contains(context.collider1()) {
+            Vect::Y
+        } else if self.platforms.contains(context.collider2()) {
+            -Vect::Y
+        } else {
+            return;
+        };
+
+        context.raw.update_as_oneway_platform(&allowed_normal.into(), 0.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ring of cells where (1, 1) and (2, 2) only touch at a corner.
+    fn pinched_ring() -> HashSet<GridCoords> {
+        [(1, 1), (0, 1), (0, 2), (0, 3), (1, 3), (2, 3), (2, 2)]
+            .into_iter()
+            .map(|(x, y)| GridCoords::new(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn pinched_outlines_do_not_cross_themselves() {
+        let regions = trace_outlines(&pinched_ring()).unwrap();
+        assert_eq!(regions.len(), 1);
+
+        let (_, loops) = &regions[0];
+        assert_eq!(loops.len(), 2);
+        for outline in loops {
+            let corners: HashSet<IVec2> = outline.iter().copied().collect();
+            assert_eq!(corners.len(), outline.len(), "loop visits a corner twice: {:?}", outline);
+        }
+    }
+
+    /// Solid cells of DevMap's only IntGrid layer, flipped so y points up like `GridCoords`.
+    fn dev_map_cells() -> (HashSet<GridCoords>, i32, i32) {
+        let (width, height) = (37, 22);
+        let ldtk = include_str!("../assets/DevMap.ldtk");
+        let start = ldtk.find("\"intGridCsv\": [").unwrap();
+        let csv = &ldtk[start..][..ldtk[start..].find(']').unwrap()];
+        let values = csv.split(|c: char| !c.is_ascii_digit()).filter(|value| !value.is_empty());
+
+        let cells = values
+            .enumerate()
+            .filter(|(_, value)| *value == "1")
+            .map(|(i, _)| GridCoords::new(i as i32 % width, height - 1 - i as i32 / width))
+            .collect();
+        (cells, width, height)
+    }
+
+    #[test]
+    fn dev_map_merges_into_few_rects() {
+        let (cells, width, height) = dev_map_cells();
+        assert_eq!(cells.len(), 402);
+
+        let rects = decompose_into_rects(&cells, width, height);
+        assert_eq!(rects.len(), 9);
+
+        let covered: Vec<GridCoords> = rects.iter().flat_map(Rect::cells).collect();
+        assert_eq!(covered.len(), cells.len(), "rects overlap");
+        assert_eq!(covered.into_iter().collect::<HashSet<_>>(), cells);
+    }
+
+    #[test]
+    fn outlines_do_not_depend_on_iteration_order() {
+        let first = trace_outlines(&pinched_ring()).unwrap();
+        for _ in 0..8 {
+            let again = trace_outlines(&pinched_ring()).unwrap();
+            assert_eq!(first[0].1, again[0].1);
         }
     }
 }
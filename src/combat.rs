@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::collections::HashSet;
+
+use crate::collisions::MergedCells;
+use crate::components::{ Breakable, Health, Player, PlayerStats };
+use crate::dialogue::dialogue_closed;
+use crate::state::GameState;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HitEvent>()
+            .add_systems(Update, spawn_player_hitbox.run_if(in_state(GameState::Playing).and_then(dialogue_closed)))
+            .add_systems(Update, (detect_hits, apply_damage).chain().run_if(in_state(GameState::Playing)))
+            .add_systems(Update, despawn_expired_hitboxes.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Sent once per target for every attack hitbox that overlaps it. Only things that can take
+/// damage get hit, walls and pickups don't.
+#[derive(Event, Clone, Debug)]
+pub struct HitEvent {
+    pub attacker: Entity,
+    /// The collider entity that was hit, something with `Health` or a breakable collider.
+    pub target: Entity,
+    pub damage: i32,
+    /// World space bounds of the hitbox that landed the hit.
+    pub area: Rect,
+}
+
+/// Short-lived sensor in front of an attacker.
+#[derive(Component)]
+pub struct AttackHitbox {
+    pub attacker: Entity,
+    pub damage: i32,
+    pub half_extents: Vec2,
+    pub lifetime: Timer,
+    pub already_hit: HashSet<Entity>,
+}
+
+const PLAYER_HALF_WIDTH: f32 = 6.0;
+const KNOCKBACK_SPEED: f32 = 200.0;
+
+fn spawn_player_hitbox(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    player: Query<(Entity, &TextureAtlasSprite, &PlayerStats), With<Player>>
+) {
+    for (entity, sprite, stats) in &player {
+        let base_damage = if input.just_pressed(KeyCode::J) {
+            1
+        } else if input.just_pressed(KeyCode::K) {
+            2
+        } else {
+            continue;
+        };
+        let damage = base_damage + stats.damage_bonus;
+
+        let direction = if sprite.flip_x { -1.0 } else { 1.0 };
+        let half_extents = Vec2::new(16.0, 20.0);
+
+        commands.entity(entity).with_children(|builder| {
+            builder
+                .spawn_empty()
+                .insert(Collider::cuboid(half_extents.x, half_extents.y))
+                .insert(Sensor)
+                .insert(ActiveEvents::COLLISION_EVENTS)
+                .insert(
+                    Transform::from_xyz(direction * (PLAYER_HALF_WIDTH + half_extents.x), 0.0, 0.0)
+                )
+                .insert(GlobalTransform::default())
+                .insert(AttackHitbox {
+                    attacker: entity,
+                    damage,
+                    half_extents,
+                    lifetime: Timer::from_seconds(0.15, TimerMode::Once),
+                    already_hit: HashSet::new(),
+                });
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn detect_hits(
+    mut collisions: EventReader<CollisionEvent>,
+    mut hitboxes: Query<(&mut AttackHitbox, &GlobalTransform)>,
+    damageable_query: Query<(), Or<(With<Health>, With<MergedCells<Breakable>>)>>,
+    mut hits: EventWriter<HitEvent>
+) {
+    for collision_event in collisions.iter() {
+        if let CollisionEvent::Started(e1, e2, _) = collision_event {
+            for (hitbox_entity, target) in [(*e1, *e2), (*e2, *e1)] {
+                if let Ok((mut hitbox, transform)) = hitboxes.get_mut(hitbox_entity) {
+                    if target == hitbox.attacker || !damageable_query.contains(target) {
+                        continue;
+                    }
+                    if hitbox.already_hit.insert(target) {
+                        hits.send(HitEvent {
+                            attacker: hitbox.attacker,
+                            target,
+                            damage: hitbox.damage,
+                            area: Rect::from_center_half_size(
+                                transform.translation().truncate(),
+                                hitbox.half_extents
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Takes the damage off and knocks the target away from whoever hit it. Breakable cells keep
+/// their own health, see `break_blocks`.
+fn apply_damage(
+    mut hits: EventReader<HitEvent>,
+    mut target_query: Query<(&mut Health, &GlobalTransform, Option<&mut Velocity>, Option<&PlayerStats>)>,
+    attacker_query: Query<&GlobalTransform>
+) {
+    for hit in hits.iter() {
+        let Ok((mut health, transform, velocity, stats)) = target_query.get_mut(hit.target) else {
+            continue;
+        };
+        let damage = match stats {
+            Some(stats) if hit.damage > 0 => (hit.damage - stats.armor).max(1),
+            _ => hit.damage,
+        };
+        health.current = (health.current - damage).max(0);
+
+        let (Some(mut velocity), Ok(attacker_transform)) = (velocity, attacker_query.get(hit.attacker)) else {
+            continue;
+        };
+        let away = (transform.translation().x - attacker_transform.translation().x).signum();
+        velocity.linvel = Vec2::new(away * KNOCKBACK_SPEED, KNOCKBACK_SPEED / 2.0);
+    }
+}
+
+fn despawn_expired_hitboxes(
+    mut commands: Commands,
+    mut hitboxes: Query<(Entity, &mut AttackHitbox)>,
+    time: Res<Time>
+) {
+    for (entity, mut hitbox) in &mut hitboxes {
+        if hitbox.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use bevy::prelude::*;
-use bevy::utils::HashMap;
-use bevy_ecs_ldtk::{ prelude::*, utils::ldtk_pixel_coords_to_translation_pivoted };
+use bevy::render::mesh::{ Indices, PrimitiveTopology };
+use bevy_ecs_ldtk::prelude::*;
 use std::collections::HashSet;
 use bevy_rapier2d::prelude::*;
 
-use crate::animation::PhoxAnimationBundle;
+use crate::collisions::{ ColliderKind, MergedCollider, MergedColliderSettings };
+use crate::interaction::Interactable;
+use crate::inventory::Inventory;
+use crate::items::{ item_collider, item_sprite_bundle, items_field, key_field, Item };
+use crate::player::{ AIR_DASHES, JUMP_SPEED, RUN_SPEED };
+use crate::signals::{ SignalReceiver, SignalSender };
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Player;
@@ -18,16 +23,228 @@ pub struct PlayerBundle {
     pub worldly: Worldly,
     pub sprite: SpriteSheetBundle,
     pub ground_detection: GroundDetection,
+    pub health: Health,
+    pub inventory: Inventory,
+    pub stats: PlayerStats,
+    pub air_dash: AirDash,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Default for Health {
+    fn default() -> Health {
+        Health { current: 5, max: 5 }
+    }
+}
+
+/// Combat and movement tuning, the defaults plus whatever the equipment adds.
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct PlayerStats {
+    pub damage_bonus: i32,
+    pub run_speed: f32,
+    pub jump_speed: f32,
+    /// Dashes the player gets per jump.
+    pub air_dashes: u32,
+    /// Taken off the damage of every hit, a hit always does at least 1.
+    pub armor: i32,
+}
+
+impl Default for PlayerStats {
+    fn default() -> PlayerStats {
+        PlayerStats {
+            damage_bonus: 0,
+            run_speed: RUN_SPEED,
+            jump_speed: JUMP_SPEED,
+            air_dashes: AIR_DASHES,
+            armor: 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct AirDash {
+    /// Dashes started since the player last touched the ground.
+    pub used: u32,
+    pub active: bool,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Wall;
 
+impl MergedCollider for Wall {
+    fn settings() -> MergedColliderSettings {
+        MergedColliderSettings {
+            layer: None,
+            kind: ColliderKind::Solid,
+            friction: 1.0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
 pub struct WallBundle {
     wall: Wall,
 }
 
+/// Thin ledge the player can jump up through and land on.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Platform;
+
+impl MergedCollider for Platform {
+    fn settings() -> MergedColliderSettings {
+        MergedColliderSettings {
+            layer: Some("Collisions"),
+            kind: ColliderKind::OneWay,
+            friction: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct PlatformBundle {
+    platform: Platform,
+}
+
+/// Wall cell that crumbles once attacks have dealt `health` damage to it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub struct Breakable {
+    pub health: i32,
+}
+
+impl Default for Breakable {
+    fn default() -> Breakable {
+        Breakable { health: 2 }
+    }
+}
+
+impl MergedCollider for Breakable {
+    fn settings() -> MergedColliderSettings {
+        MergedColliderSettings {
+            layer: Some("Collisions"),
+            kind: ColliderKind::Solid,
+            friction: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct BreakableBundle {
+    breakable: Breakable,
+}
+
+/// Spikes and the like, the player dies on touching one.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Hazard;
+
+impl MergedCollider for Hazard {
+    fn settings() -> MergedColliderSettings {
+        MergedColliderSettings {
+            layer: Some("Collisions"),
+            kind: ColliderKind::Sensor,
+            friction: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct HazardBundle {
+    hazard: Hazard,
+}
+
+/// Hazards have no tile in the tileset, they're drawn as plain cells in this colour.
+pub const HAZARD_COLOR: Color = Color::rgb(0.7, 0.13, 0.13);
+
+/// Roughly the colour of the wall tiles, so slopes blend in with the ground around them.
+pub const SLOPE_COLOR: Color = Color::rgb(0.15, 0.17, 0.18);
+
+/// Sloped ground cell. Variants are named after the direction the surface rises in,
+/// the 22.5° slopes take two cells each (a low half and a high half).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub enum Slope {
+    #[default]
+    RisingRight45,
+    RisingLeft45,
+    RisingRightLow22,
+    RisingRightHigh22,
+    RisingLeftLow22,
+    RisingLeftHigh22,
+}
+
+impl From<IntGridCell> for Slope {
+    fn from(int_grid_cell: IntGridCell) -> Slope {
+        match int_grid_cell.value {
+            3 => Slope::RisingLeft45,
+            4 => Slope::RisingRightLow22,
+            5 => Slope::RisingRightHigh22,
+            6 => Slope::RisingLeftLow22,
+            7 => Slope::RisingLeftHigh22,
+            _ => Slope::RisingRight45,
+        }
+    }
+}
+
+impl Slope {
+    /// Surface height at the left and right edge of the cell, as a fraction of the cell size.
+    pub fn heights(&self) -> (f32, f32) {
+        match self {
+            Slope::RisingRight45 => (0.0, 1.0),
+            Slope::RisingLeft45 => (1.0, 0.0),
+            Slope::RisingRightLow22 => (0.0, 0.5),
+            Slope::RisingRightHigh22 => (0.5, 1.0),
+            Slope::RisingLeftLow22 => (0.5, 0.0),
+            Slope::RisingLeftHigh22 => (1.0, 0.5),
+        }
+    }
+
+    pub fn normal(&self) -> Vec2 {
+        let (left, right) = self.heights();
+        Vec2::new(left - right, 1.0).normalize()
+    }
+
+    /// Triangle or trapezoid filling the cell below the surface, centered on the cell.
+    /// Corners go counter-clockwise from the bottom left.
+    fn outline(&self, grid_size: f32) -> Vec<Vec2> {
+        let half = grid_size / 2.0;
+        let (left, right) = self.heights();
+
+        let mut points = vec![Vec2::new(-half, -half), Vec2::new(half, -half)];
+        if right > 0.0 {
+            points.push(Vec2::new(half, right * grid_size - half));
+        }
+        if left > 0.0 {
+            points.push(Vec2::new(-half, left * grid_size - half));
+        }
+        points
+    }
+
+    pub fn collider(&self, grid_size: f32) -> Collider {
+        Collider::convex_hull(&self.outline(grid_size))
+            .expect("Slope outline should be a valid convex polygon")
+    }
+
+    /// Same outline as the collider, since slopes have no tile in the tileset.
+    pub fn mesh(&self, grid_size: f32) -> Mesh {
+        let outline = self.outline(grid_size);
+        let positions: Vec<[f32; 3]> = outline.iter().map(|point| [point.x, point.y, 0.0]).collect();
+        let indices = (1..outline.len() as u32 - 1).flat_map(|i| [0, i, i + 1]).collect();
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct SlopeBundle {
+    #[from_int_grid_cell]
+    slope: Slope,
+}
+
 #[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
 pub struct ColliderBundle {
     pub collider: Collider,
@@ -55,13 +272,418 @@ impl From<&EntityInstance> for ColliderBundle {
                     rotation_constraints,
                     ..Default::default()
                 },
+            "Item" => item_collider(),
+            "MovingPlatform" =>
+                ColliderBundle {
+                    collider: Collider::cuboid(
+                        (entity_instance.width as f32) / 2.0,
+                        (entity_instance.height as f32) / 2.0
+                    ),
+                    rigid_body: RigidBody::KinematicVelocityBased,
+                    friction: Friction::coefficient(1.0),
+                    rotation_constraints,
+                    ..Default::default()
+                },
             _ => ColliderBundle::default(),
         }
     }
 }
+/// Interacting with it takes the player to `destination`.
+/// Locked doors are solid until opened with a key, mechanical ones only open while signalled.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Door {
+    pub destination: Option<ldtk::ReferenceToAnEntityInstance>,
+    pub half_size: Vec2,
+    pub locked: bool,
+    /// Only an `Item::Key` with this id unlocks it.
+    pub key: u32,
+    pub mechanical: bool,
+}
+
+impl From<&EntityInstance> for Door {
+    fn from(entity_instance: &EntityInstance) -> Door {
+        Door {
+            destination: entity_instance
+                .get_maybe_entity_ref_field("Destination")
+                .ok()
+                .cloned()
+                .flatten(),
+            half_size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32) / 2.0,
+            locked: entity_instance.get_bool_field("locked").copied().unwrap_or(false),
+            key: key_field(entity_instance),
+            mechanical: entity_instance.get_bool_field("mechanical").copied().unwrap_or(false),
+        }
+    }
+}
+
+fn door_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(0.3, 0.2, 0.15),
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct DoorBundle {
+    #[from_entity_instance]
+    pub door: Door,
+    #[with(door_sprite)]
+    pub sprite: SpriteBundle,
+    pub receiver: SignalReceiver,
+}
+
+/// Becomes the respawn point once the player touches it.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Checkpoint {
+    pub half_size: Vec2,
+    pub active: bool,
+}
+
+impl From<&EntityInstance> for Checkpoint {
+    fn from(entity_instance: &EntityInstance) -> Checkpoint {
+        Checkpoint {
+            half_size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32) / 2.0,
+            active: false,
+        }
+    }
+}
+
+pub const CHECKPOINT_COLOR: Color = Color::rgb(0.45, 0.45, 0.5);
+pub const CHECKPOINT_ACTIVE_COLOR: Color = Color::rgb(0.9, 0.78, 0.3);
+
+fn checkpoint_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: CHECKPOINT_COLOR,
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct CheckpointBundle {
+    #[from_entity_instance]
+    pub checkpoint: Checkpoint,
+    #[with(checkpoint_sprite)]
+    pub sprite: SpriteBundle,
+}
+
+/// Opened by interacting with it, drops everything in `content`.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Chest {
+    pub content: Vec<Item>,
+}
+
+impl From<&EntityInstance> for Chest {
+    fn from(entity_instance: &EntityInstance) -> Chest {
+        Chest {
+            content: items_field(entity_instance, "content")
+                .into_iter()
+                .map(|item| item.with_key(key_field(entity_instance)))
+                .collect(),
+        }
+    }
+}
+
+pub const CHEST_COLOR: Color = Color::rgb(0.55, 0.36, 0.2);
+pub const CHEST_OPEN_COLOR: Color = Color::rgb(0.3, 0.2, 0.12);
+
+fn chest_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: CHEST_COLOR,
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn chest_interactable(entity_instance: &EntityInstance) -> Interactable {
+    Interactable::new(entity_instance.width as f32 / 2.0, "Open")
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct ChestBundle {
+    #[from_entity_instance]
+    pub chest: Chest,
+    #[with(chest_sprite)]
+    pub sprite: SpriteBundle,
+    #[with(chest_interactable)]
+    pub interactable: Interactable,
+}
+
+/// Item lying around in a level, collected by walking into it.
+#[derive(Clone, Debug, Component)]
+pub struct ItemPickup {
+    pub item: Item,
+    /// Keeps fresh drops from being collected before they've left the chest.
+    pub delay: Timer,
+}
+
+impl From<&EntityInstance> for ItemPickup {
+    fn from(entity_instance: &EntityInstance) -> ItemPickup {
+        let item = entity_instance
+            .get_enum_field("type")
+            .ok()
+            .and_then(|value| Item::from_ldtk(value))
+            .unwrap_or_else(|| {
+                warn!("Item {} has no valid type, defaulting to a gem", entity_instance.iid);
+                Item::Gem
+            });
+
+        ItemPickup::new(item.with_key(key_field(entity_instance)), 0.0)
+    }
+}
+
+impl Default for ItemPickup {
+    fn default() -> ItemPickup {
+        ItemPickup::new(Item::Gem, 0.0)
+    }
+}
+
+impl ItemPickup {
+    pub fn new(item: Item, delay: f32) -> ItemPickup {
+        ItemPickup {
+            item,
+            delay: Timer::from_seconds(delay, TimerMode::Once),
+        }
+    }
+}
+
+fn item_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    item_sprite_bundle(ItemPickup::from(entity_instance).item)
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct ItemBundle {
+    #[from_entity_instance]
+    pub pickup: ItemPickup,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[with(item_sprite)]
+    pub sprite: SpriteBundle,
+}
+
+/// Talks to the player, `dialogue` is the script to load from `assets/dialogue`.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Npc {
+    pub dialogue: String,
+}
+
+impl From<&EntityInstance> for Npc {
+    fn from(entity_instance: &EntityInstance) -> Npc {
+        Npc {
+            dialogue: entity_instance.get_string_field("dialogue").cloned().unwrap_or_default(),
+        }
+    }
+}
+
+fn npc_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(0.37, 0.8, 0.89),
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn npc_interactable(entity_instance: &EntityInstance) -> Interactable {
+    Interactable::new(entity_instance.width as f32, "Talk")
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct NpcBundle {
+    #[from_entity_instance]
+    pub npc: Npc,
+    #[with(npc_sprite)]
+    pub sprite: SpriteBundle,
+    #[with(npc_interactable)]
+    pub interactable: Interactable,
+}
+
+/// Switched on and off by interacting with it, stays that way.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Lever;
+
+pub const LEVER_COLOR: Color = Color::rgb(0.5, 0.45, 0.4);
+pub const LEVER_PULLED_COLOR: Color = Color::rgb(0.85, 0.7, 0.3);
+
+fn lever_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: LEVER_COLOR,
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn lever_interactable(entity_instance: &EntityInstance) -> Interactable {
+    Interactable::new(entity_instance.width as f32, "Pull")
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct LeverBundle {
+    pub lever: Lever,
+    #[from_entity_instance]
+    pub sender: SignalSender,
+    #[with(lever_sprite)]
+    pub sprite: SpriteBundle,
+    #[with(lever_interactable)]
+    pub interactable: Interactable,
+}
+
+/// On while anything heavy enough is standing on it.
+#[derive(Clone, Debug, Default, Component)]
+pub struct PressurePlate {
+    pub half_size: Vec2,
+    /// Bodies currently inside the plate's sensor.
+    pub pressing: HashSet<Entity>,
+}
+
+impl From<&EntityInstance> for PressurePlate {
+    fn from(entity_instance: &EntityInstance) -> PressurePlate {
+        PressurePlate {
+            half_size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32) / 2.0,
+            pressing: HashSet::new(),
+        }
+    }
+}
+
+fn pressure_plate_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(0.6, 0.55, 0.5),
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct PressurePlateBundle {
+    #[from_entity_instance]
+    pub plate: PressurePlate,
+    #[from_entity_instance]
+    pub sender: SignalSender,
+    #[with(pressure_plate_sprite)]
+    pub sprite: SpriteBundle,
+}
+
+/// Invisible area that's on while the player is inside it, or for good once entered if `once`.
+#[derive(Clone, Debug, Default, Component)]
+pub struct TriggerVolume {
+    pub half_size: Vec2,
+    pub once: bool,
+}
+
+impl From<&EntityInstance> for TriggerVolume {
+    fn from(entity_instance: &EntityInstance) -> TriggerVolume {
+        TriggerVolume {
+            half_size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32) / 2.0,
+            once: entity_instance.get_bool_field("once").copied().unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct TriggerVolumeBundle {
+    #[from_entity_instance]
+    pub trigger: TriggerVolume,
+    #[from_entity_instance]
+    pub sender: SignalSender,
+}
+
+/// Travels to its `end` point while signalled and back to where it was placed when not.
+#[derive(Clone, Debug, Default, Component)]
+pub struct MovingPlatform {
+    /// From the platform to `end` in grid cells, LDtk's y down.
+    pub travel: IVec2,
+    pub speed: f32,
+    /// Resolved against the layer's grid once spawned, relative to the layer.
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl From<&EntityInstance> for MovingPlatform {
+    fn from(entity_instance: &EntityInstance) -> MovingPlatform {
+        MovingPlatform {
+            travel: entity_instance
+                .get_point_field("end")
+                .map(|end| *end - entity_instance.grid)
+                .unwrap_or_default(),
+            speed: entity_instance.get_float_field("speed").copied().unwrap_or(60.0),
+            start: Vec2::ZERO,
+            end: Vec2::ZERO,
+        }
+    }
+}
+
+fn moving_platform_sprite(entity_instance: &EntityInstance) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(0.45, 0.4, 0.5),
+            custom_size: Some(Vec2::new(entity_instance.width as f32, entity_instance.height as f32)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct MovingPlatformBundle {
+    #[from_entity_instance]
+    pub platform: MovingPlatform,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[with(moving_platform_sprite)]
+    pub sprite: SpriteBundle,
+    pub receiver: SignalReceiver,
+}
+
+/// Drops an `item` every time it's switched on.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Spawner {
+    pub item: Option<Item>,
+}
+
+impl From<&EntityInstance> for Spawner {
+    fn from(entity_instance: &EntityInstance) -> Spawner {
+        let item = entity_instance
+            .get_enum_field("item")
+            .ok()
+            .and_then(|value| Item::from_ldtk(value));
+        if item.is_none() {
+            warn!("Spawner {} has no valid item, it won't spawn anything", entity_instance.iid);
+        }
+
+        Spawner { item }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SpawnerBundle {
+    #[from_entity_instance]
+    pub spawner: Spawner,
+    pub receiver: SignalReceiver,
+}
+
 #[derive(Clone, Default, Component)]
 pub struct GroundDetection {
     pub on_ground: bool,
+    /// Set while standing only on slopes, flat ground takes priority.
+    pub slope: Option<Slope>,
 }
 
 #[derive(Component)]
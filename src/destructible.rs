@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::HashSet;
+
+use crate::collisions::MergedCells;
+use crate::combat::HitEvent;
+use crate::components::Breakable;
+use crate::effects::{ spawn_crumble, spawn_particle_burst };
+use crate::state::GameState;
+
+pub struct DestructiblePlugin;
+
+impl Plugin for DestructiblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlockBrokenEvent>()
+            .add_systems(Update, break_blocks.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Sent after a `Breakable` cell was removed, loot drops hook in here.
+#[derive(Event, Clone, Debug)]
+pub struct BlockBrokenEvent {
+    pub level: Entity,
+    pub grid_coords: GridCoords,
+    pub translation: Vec3,
+}
+
+const DEBRIS_COLOR: Color = Color::rgb(0.35, 0.22, 0.16);
+
+/// Damages every `Breakable` cell under a hitbox that hit one of their merged colliders,
+/// and breaks the ones that run out of health. Removing the cells makes
+/// `spawn_merged_collision::<Breakable>` rebuild the colliders.
+pub fn break_blocks(
+    mut commands: Commands,
+    mut hits: EventReader<HitEvent>,
+    breakable_colliders: Query<&Parent, With<MergedCells<Breakable>>>,
+    mut breakable_cells: Query<(Entity, &GridCoords, &Parent, &mut Breakable)>,
+    layer_query: Query<(&Parent, &LayerMetadata)>,
+    level_query: Query<&GlobalTransform>,
+    mut broken_blocks: EventWriter<BlockBrokenEvent>
+) {
+    let mut broken: HashSet<Entity> = HashSet::new();
+
+    for hit in hits.iter() {
+        let Ok(collider_parent) = breakable_colliders.get(hit.target) else {
+            continue;
+        };
+        let level_entity = collider_parent.get();
+        let Ok(level_transform) = level_query.get(level_entity) else {
+            continue;
+        };
+        let level_translation = level_transform.translation();
+
+        // Hitbox bounds relative to the level, where the cells live
+        let area = Rect::from_corners(
+            hit.area.min - level_translation.truncate(),
+            hit.area.max - level_translation.truncate()
+        );
+
+        for (cell_entity, grid_coords, parent, mut breakable) in &mut breakable_cells {
+            let Ok((grandparent, layer)) = layer_query.get(parent.get()) else {
+                continue;
+            };
+            if grandparent.get() != level_entity {
+                continue;
+            }
+
+            let grid_size = layer.grid_size as f32;
+            let cell_min = Vec2::new(grid_coords.x as f32, grid_coords.y as f32) * grid_size;
+            let cell = Rect::from_corners(cell_min, cell_min + grid_size);
+
+            if cell.intersect(area).is_empty() || broken.contains(&cell_entity) {
+                continue;
+            }
+
+            breakable.health -= hit.damage;
+            if breakable.health > 0 {
+                continue;
+            }
+            broken.insert(cell_entity);
+
+            commands.entity(cell_entity).despawn_recursive();
+
+            let translation = level_translation + cell.center().extend(10.0);
+            spawn_crumble(&mut commands, translation, Vec2::splat(grid_size), DEBRIS_COLOR);
+            spawn_particle_burst(&mut commands, translation, DEBRIS_COLOR, 8);
+
+            broken_blocks.send(BlockBrokenEvent {
+                level: level_entity,
+                grid_coords: *grid_coords,
+                translation,
+            });
+        }
+    }
+}
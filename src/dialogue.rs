@@ -0,0 +1,449 @@
+use bevy::asset::{ AssetLoader, LoadContext, LoadedAsset };
+use bevy::prelude::*;
+use bevy::reflect::{ TypePath, TypeUuid };
+use bevy::utils::BoxedFuture;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::components::{ Npc, Player };
+use crate::interaction::InteractEvent;
+use crate::state::GameState;
+use crate::systems::RestartWorld;
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<DialogueScript>()
+            .init_asset_loader::<DialogueLoader>()
+            .init_resource::<Dialogue>()
+            .add_systems(Update, load_npc_dialogue)
+            // Advancing first so the press that starts a conversation doesn't also skip its first page
+            .add_systems(Update, (advance_dialogue, start_dialogue).chain().run_if(in_state(GameState::Playing)))
+            .add_systems(Update, type_dialogue_text.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, spawn_dialogue_box)
+            .add_systems(Update, reset_dialogue);
+    }
+}
+
+/// A conversation loaded from a `.dialogue.ron` file, a graph of nodes the player walks
+/// through by reading pages and picking choices.
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "6f3b4f0e-2f7c-4f0d-9a4b-6c1d2e8f5a31"]
+pub struct DialogueScript {
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DialogueNode {
+    pub speaker: String,
+    /// Image in `assets`, speakers without one get a plain box with their initial.
+    #[serde(default)]
+    pub portrait: Option<String>,
+    pub pages: Vec<String>,
+    /// Offered after the last page, the node ends with `next` if there are none.
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// `None` ends the conversation.
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Default)]
+pub struct DialogueLoader;
+
+impl AssetLoader for DialogueLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let script: DialogueScript = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(script));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue.ron"]
+    }
+}
+
+/// Where the player is in the open conversation.
+pub struct Conversation {
+    pub script: Handle<DialogueScript>,
+    pub node: String,
+    pub page: usize,
+    /// Characters of the page typed out so far.
+    pub revealed: f32,
+    pub selected: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct Dialogue {
+    pub conversation: Option<Conversation>,
+}
+
+/// Run condition for everything the player shouldn't be doing mid-conversation.
+pub fn dialogue_closed(dialogue: Res<Dialogue>) -> bool {
+    dialogue.conversation.is_none()
+}
+
+/// Script an NPC talks with, loaded as soon as the NPC spawns.
+#[derive(Component)]
+pub struct NpcDialogue(pub Handle<DialogueScript>);
+
+#[derive(Component)]
+pub struct DialogueBox;
+
+#[derive(Component)]
+pub struct DialogueText;
+
+const CHARACTERS_PER_SECOND: f32 = 40.0;
+const DIALOGUE_BACKGROUND: Color = Color::rgba(0.05, 0.05, 0.08, 0.92);
+const SPEAKER_COLOR: Color = Color::rgb(0.9, 0.78, 0.3);
+const CHOICE_COLOR: Color = Color::rgb(0.6, 0.6, 0.65);
+
+const CONFIRM_KEYS: [KeyCode; 3] = [KeyCode::E, KeyCode::Space, KeyCode::Return];
+const CONFIRM_BUTTONS: [GamepadButtonType; 2] = [GamepadButtonType::South, GamepadButtonType::West];
+/// How far the left stick has to be pushed to move the choice selection.
+const STICK_THRESHOLD: f32 = 0.5;
+
+fn load_npc_dialogue(
+    mut commands: Commands,
+    npc_query: Query<(Entity, &Npc), Added<Npc>>,
+    asset_server: Res<AssetServer>
+) {
+    for (entity, npc) in &npc_query {
+        if npc.dialogue.is_empty() {
+            warn!("NPC without a dialogue script");
+            continue;
+        }
+
+        let script = asset_server.load(format!("dialogue/{}.dialogue.ron", npc.dialogue));
+        commands.entity(entity).insert(NpcDialogue(script));
+    }
+}
+
+fn start_dialogue(
+    mut interactions: EventReader<InteractEvent>,
+    mut dialogue: ResMut<Dialogue>,
+    npc_query: Query<&NpcDialogue>,
+    scripts: Res<Assets<DialogueScript>>,
+    mut player_query: Query<&mut Velocity, With<Player>>
+) {
+    for interaction in interactions.iter() {
+        let Ok(NpcDialogue(handle)) = npc_query.get(interaction.target) else {
+            continue;
+        };
+        let Some(script) = scripts.get(handle) else {
+            warn!("Dialogue script isn't loaded yet");
+            continue;
+        };
+        if !script.nodes.contains_key(&script.start) {
+            error!("Dialogue script has no start node {}", script.start);
+            continue;
+        }
+
+        dialogue.conversation = Some(Conversation {
+            script: handle.clone(),
+            node: script.start.clone(),
+            page: 0,
+            revealed: 0.0,
+            selected: 0,
+        });
+
+        // Movement stops running while talking, don't leave the player sliding
+        for mut velocity in &mut player_query {
+            velocity.linvel.x = 0.0;
+        }
+    }
+}
+
+/// Confirm finishes typing the page, then turns it, then picks the selected choice.
+/// Choices are picked with W/S, the arrow keys, the d-pad or the left stick.
+fn advance_dialogue(
+    mut input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut stick_direction: Local<i32>,
+    mut dialogue: ResMut<Dialogue>,
+    scripts: Res<Assets<DialogueScript>>
+) {
+    // The stick has to go back to the middle before it moves the selection again
+    let stick_y = gamepads
+        .iter()
+        .filter_map(|gamepad| gamepad_axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)))
+        .fold(0.0, |stick_y: f32, value| if value.abs() > stick_y.abs() { value } else { stick_y });
+    let direction = if stick_y > STICK_THRESHOLD {
+        1
+    } else if stick_y < -STICK_THRESHOLD {
+        -1
+    } else {
+        0
+    };
+    let stick_moved = direction != 0 && direction != *stick_direction;
+    *stick_direction = direction;
+
+    let Some(conversation) = &dialogue.conversation else {
+        return;
+    };
+    let Some(node) = scripts.get(&conversation.script).and_then(|script| script.nodes.get(&conversation.node)) else {
+        dialogue.conversation = None;
+        return;
+    };
+
+    let page_length = node.pages
+        .get(conversation.page)
+        .map_or(0, |page| page.chars().count());
+    let typed = conversation.revealed >= (page_length as f32);
+    let last_page = conversation.page + 1 >= node.pages.len();
+    let choosing = typed && last_page && !node.choices.is_empty();
+
+    if choosing {
+        let dpad_pressed = |button| {
+            gamepads.iter().any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button)))
+        };
+        let up =
+            input.any_just_pressed([KeyCode::W, KeyCode::Up]) ||
+            dpad_pressed(GamepadButtonType::DPadUp) ||
+            (stick_moved && direction > 0);
+        let down =
+            input.any_just_pressed([KeyCode::S, KeyCode::Down]) ||
+            dpad_pressed(GamepadButtonType::DPadDown) ||
+            (stick_moved && direction < 0);
+        let count = node.choices.len();
+        let selected = conversation.selected;
+        if up {
+            dialogue.conversation.as_mut().unwrap().selected = (selected + count - 1) % count;
+        } else if down {
+            dialogue.conversation.as_mut().unwrap().selected = (selected + 1) % count;
+        }
+    }
+
+    // Clearing the presses keeps the interaction that would otherwise fire this frame from
+    // reopening a conversation that was just closed
+    let mut confirmed = false;
+    for key in CONFIRM_KEYS {
+        confirmed |= input.clear_just_pressed(key);
+    }
+    for gamepad in gamepads.iter() {
+        for button in CONFIRM_BUTTONS {
+            confirmed |= gamepad_buttons.clear_just_pressed(GamepadButton::new(gamepad, button));
+        }
+    }
+    if !confirmed {
+        return;
+    }
+
+    let conversation = dialogue.conversation.as_mut().unwrap();
+    if !typed {
+        conversation.revealed = page_length as f32;
+        return;
+    }
+    if !last_page {
+        conversation.page += 1;
+        conversation.revealed = 0.0;
+        return;
+    }
+
+    let next = if choosing { node.choices[conversation.selected].next.clone() } else { node.next.clone() };
+    match next {
+        Some(next) if scripts.get(&conversation.script).is_some_and(|script| script.nodes.contains_key(&next)) => {
+            conversation.node = next;
+            conversation.page = 0;
+            conversation.revealed = 0.0;
+            conversation.selected = 0;
+        }
+        Some(next) => {
+            error!("Dialogue node {} doesn't exist", next);
+            dialogue.conversation = None;
+        }
+        None => {
+            dialogue.conversation = None;
+        }
+    }
+}
+
+/// Only touches the text while typing, the box itself gets rebuilt once the page is done
+/// so the choices show up.
+fn type_dialogue_text(
+    mut dialogue: ResMut<Dialogue>,
+    scripts: Res<Assets<DialogueScript>>,
+    mut text_query: Query<&mut Text, With<DialogueText>>,
+    time: Res<Time>
+) {
+    let Some(conversation) = &dialogue.bypass_change_detection().conversation else {
+        return;
+    };
+    let Some(page) = scripts
+        .get(&conversation.script)
+        .and_then(|script| script.nodes.get(&conversation.node))
+        .and_then(|node| node.pages.get(conversation.page)) else {
+        return;
+    };
+
+    let page_length = page.chars().count() as f32;
+    if conversation.revealed >= page_length {
+        return;
+    }
+
+    let revealed = (conversation.revealed + CHARACTERS_PER_SECOND * time.delta_seconds()).min(page_length);
+    if revealed >= page_length {
+        dialogue.conversation.as_mut().unwrap().revealed = revealed;
+    } else {
+        dialogue.bypass_change_detection().conversation.as_mut().unwrap().revealed = revealed;
+    }
+
+    for mut text in &mut text_query {
+        text.sections[0].value = page.chars().take(revealed as usize).collect();
+    }
+}
+
+fn spawn_dialogue_box(
+    mut commands: Commands,
+    dialogue: Res<Dialogue>,
+    scripts: Res<Assets<DialogueScript>>,
+    asset_server: Res<AssetServer>,
+    box_query: Query<Entity, With<DialogueBox>>
+) {
+    if !dialogue.is_changed() {
+        return;
+    }
+
+    for entity in &box_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(conversation) = &dialogue.conversation else {
+        return;
+    };
+    let Some(node) = scripts.get(&conversation.script).and_then(|script| script.nodes.get(&conversation.node)) else {
+        return;
+    };
+
+    let page = node.pages.get(conversation.page).cloned().unwrap_or_default();
+    let typed = conversation.revealed >= (page.chars().count() as f32);
+    let shown: String = page.chars().take(conversation.revealed as usize).collect();
+    let show_choices = typed && conversation.page + 1 >= node.pages.len();
+
+    let root = NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(5.0),
+            right: Val::Percent(5.0),
+            bottom: Val::Px(16.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            column_gap: Val::Px(12.0),
+            ..default()
+        },
+        background_color: DIALOGUE_BACKGROUND.into(),
+        z_index: ZIndex::Global(40),
+        ..default()
+    };
+
+    commands
+        .spawn((root, DialogueBox))
+        .with_children(|parent| {
+            let portrait_style = Style {
+                width: Val::Px(64.0),
+                height: Val::Px(64.0),
+                flex_shrink: 0.0,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            };
+            match &node.portrait {
+                Some(portrait) => {
+                    parent.spawn(ImageBundle {
+                        style: portrait_style,
+                        image: UiImage::new(asset_server.load(portrait.as_str())),
+                        ..default()
+                    });
+                }
+                None => {
+                    let initial = node.speaker.chars().next().unwrap_or('?').to_string();
+                    parent
+                        .spawn(NodeBundle {
+                            style: portrait_style,
+                            background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+                            ..default()
+                        })
+                        .with_children(|portrait| {
+                            portrait.spawn(
+                                TextBundle::from_section(initial, TextStyle {
+                                    font_size: 40.0,
+                                    color: SPEAKER_COLOR,
+                                    ..default()
+                                })
+                            );
+                        });
+                }
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        flex_grow: 1.0,
+                        row_gap: Val::Px(6.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|column| {
+                    column.spawn(
+                        TextBundle::from_section(node.speaker.clone(), TextStyle {
+                            font_size: 22.0,
+                            color: SPEAKER_COLOR,
+                            ..default()
+                        })
+                    );
+                    column.spawn((
+                        TextBundle::from_section(shown, TextStyle {
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                            ..default()
+                        }),
+                        DialogueText,
+                    ));
+
+                    if !show_choices {
+                        return;
+                    }
+                    for (i, choice) in node.choices.iter().enumerate() {
+                        let (marker, color) = if i == conversation.selected {
+                            ("> ", Color::WHITE)
+                        } else {
+                            ("  ", CHOICE_COLOR)
+                        };
+                        column.spawn(
+                            TextBundle::from_section(format!("{}{}", marker, choice.text), TextStyle {
+                                font_size: 20.0,
+                                color,
+                                ..default()
+                            })
+                        );
+                    }
+                });
+        });
+}
+
+fn reset_dialogue(mut restarts: EventReader<RestartWorld>, mut dialogue: ResMut<Dialogue>) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    dialogue.conversation = None;
+}
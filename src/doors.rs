@@ -0,0 +1,407 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy_ecs_ldtk::{ prelude::*, utils::ldtk_pixel_coords_to_translation_pivoted };
+use bevy_rapier2d::prelude::*;
+
+use crate::components::{ Door, Player };
+use crate::interaction::{ InteractEvent, Interactable };
+use crate::inventory::{ Inventory, InventoryEvent };
+use crate::items::Item;
+use crate::save::WaitingForLevel;
+use crate::signals::SignalReceiver;
+use crate::state::GameState;
+use crate::systems::{ CameraLevel, RestartWorld };
+use crate::world_state::{ EntityState, Opened, WorldState };
+
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DoorTransition>()
+            .add_systems(Startup, spawn_fade_overlay)
+            // Mechanical doors need their starting collider before they can tell if they're closed
+            .add_systems(Update, (setup_doors, power_mechanical_doors).chain())
+            .add_systems(Update, show_unlocked_doors)
+            // Entering first so the press that unlocks a door doesn't also go through it
+            .add_systems(Update, (enter_door, unlock_doors).chain().run_if(in_state(GameState::Playing)))
+            .add_systems(Update, animate_door_opening.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, run_door_transition.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, reset_door_transition);
+    }
+}
+
+const FADE_SECONDS: f32 = 0.25;
+const DOOR_OPEN_SECONDS: f32 = 0.5;
+/// How far past its frame a door can be used from, the player can't stand inside a locked one.
+const INTERACT_REACH: f32 = 12.0;
+/// How much of an open door is left showing at the top of its frame.
+const OPEN_DOOR_SLIVER: f32 = 0.1;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum TransitionState {
+    #[default]
+    Idle,
+    FadingOut,
+    FadingIn,
+}
+
+/// Fade to black, move the player to the destination door, fade back in.
+#[derive(Resource, Default)]
+pub struct DoorTransition {
+    pub state: TransitionState,
+    timer: Timer,
+    /// Iid of the destination level and where the player's feet go.
+    destination: Option<(String, Vec2)>,
+}
+
+/// Full screen black node whose alpha is driven by the door transition.
+#[derive(Component)]
+pub struct FadeOverlay;
+
+fn spawn_fade_overlay(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            z_index: ZIndex::Global(100),
+            ..default()
+        },
+        FadeOverlay,
+    ));
+}
+
+/// Finds the level and the world position of the bottom center of a referenced entity,
+/// straight from the project so the destination level doesn't need to be spawned.
+fn resolve_destination(
+    ldtk_asset: &LdtkAsset,
+    reference: &ldtk::ReferenceToAnEntityInstance
+) -> Option<(String, Vec2)> {
+    let level = ldtk_asset.get_level(&LevelSelection::Iid(reference.level_iid.clone()))?;
+    let entity_instance = level.layer_instances
+        .as_ref()?
+        .iter()
+        .flat_map(|layer| &layer.entity_instances)
+        .find(|entity_instance| entity_instance.iid == reference.entity_iid)?;
+
+    let size = IVec2::new(entity_instance.width, entity_instance.height);
+    let center = ldtk_pixel_coords_to_translation_pivoted(
+        entity_instance.px,
+        level.px_hei,
+        size,
+        entity_instance.pivot
+    );
+    let level_translation = Vec2::new(level.world_x as f32, -(level.world_y + level.px_hei) as f32);
+
+    Some((level.iid.clone(), level_translation + center - Vec2::new(0.0, (size.y as f32) / 2.0)))
+}
+
+fn enter_door(
+    mut interactions: EventReader<InteractEvent>,
+    mut transition: ResMut<DoorTransition>,
+    door_query: Query<&Door>,
+    world_query: Query<&Handle<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>
+) {
+    for interaction in interactions.iter() {
+        if transition.state != TransitionState::Idle {
+            continue;
+        }
+        let Ok(door) = door_query.get(interaction.target) else {
+            continue;
+        };
+        if door.locked {
+            continue;
+        }
+        let Some(reference) = &door.destination else {
+            continue;
+        };
+
+        let destination = world_query
+            .iter()
+            .filter_map(|handle| ldtk_assets.get(handle))
+            .find_map(|ldtk_asset| resolve_destination(ldtk_asset, reference));
+
+        if destination.is_none() {
+            warn!("Door destination {} could not be found", reference.entity_iid);
+            continue;
+        }
+
+        transition.destination = destination;
+        transition.state = TransitionState::FadingOut;
+        transition.timer = Timer::from_seconds(FADE_SECONDS, TimerMode::Once);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn run_door_transition(
+    mut commands: Commands,
+    mut transition: ResMut<DoorTransition>,
+    mut level_selection: ResMut<LevelSelection>,
+    camera_level: Res<CameraLevel>,
+    mut player_query: Query<
+        (Entity, &mut Transform, &mut Velocity, &mut GravityScale, &Collider),
+        With<Player>
+    >,
+    mut overlay_query: Query<&mut BackgroundColor, With<FadeOverlay>>,
+    time: Res<Time>
+) {
+    if transition.state == TransitionState::Idle {
+        return;
+    }
+
+    transition.timer.tick(time.delta());
+    let alpha = match transition.state {
+        TransitionState::FadingOut => transition.timer.percent(),
+        _ => transition.timer.percent_left(),
+    };
+    for mut background_color in &mut overlay_query {
+        background_color.0 = Color::rgba(0.0, 0.0, 0.0, alpha);
+    }
+
+    if !transition.timer.finished() {
+        return;
+    }
+
+    match transition.state {
+        TransitionState::FadingOut => {
+            if let Some((level_iid, feet)) = transition.destination.take() {
+                let changes_level = camera_level.iid.as_ref() != Some(&level_iid);
+
+                for (entity, mut transform, mut velocity, mut gravity, collider) in &mut player_query {
+                    let half_height = collider
+                        .as_cuboid()
+                        .map(|cuboid| cuboid.half_extents().y)
+                        .unwrap_or_default();
+                    transform.translation.x = feet.x;
+                    transform.translation.y = feet.y + half_height;
+                    velocity.linvel = Vec2::ZERO;
+
+                    // Another level's colliders aren't there yet, hold the player until they are
+                    if changes_level {
+                        *gravity = GravityScale(0.0);
+                        commands.entity(entity).insert(WaitingForLevel { level_iid: level_iid.clone() });
+                    }
+                }
+                *level_selection = LevelSelection::Iid(level_iid);
+            }
+
+            transition.state = TransitionState::FadingIn;
+            transition.timer = Timer::from_seconds(FADE_SECONDS, TimerMode::Once);
+        }
+        _ => {
+            transition.state = TransitionState::Idle;
+        }
+    }
+}
+
+/// Rises into the top of its frame, or drops back down from it when `closing`.
+#[derive(Component)]
+pub struct DoorOpening {
+    timer: Timer,
+    closing: bool,
+}
+
+/// Shrinks the sprite towards the top of the frame, the transform is left alone so the
+/// interaction sensor keeps its size.
+fn set_door_open_amount(sprite: &mut Sprite, size: Vec2, amount: f32) {
+    let height = size.y * (1.0 - (1.0 - OPEN_DOOR_SLIVER) * amount);
+    sprite.custom_size = Some(Vec2::new(size.x, height));
+    sprite.anchor = Anchor::Custom(Vec2::new(0.0, 0.5 - size.y / 2.0 / height));
+}
+
+/// Locked and mechanical doors start out solid, and the ones with a lock to open or somewhere
+/// to go are interactable. Mechanical doors only become interactable once they're open.
+fn setup_doors(mut commands: Commands, door_query: Query<(Entity, &Door), Added<Door>>) {
+    for (entity, door) in &door_query {
+        let mut entity_commands = commands.entity(entity);
+        if door.locked || door.mechanical {
+            entity_commands.insert((RigidBody::Fixed, Collider::cuboid(door.half_size.x, door.half_size.y)));
+        }
+
+        let prompt = if door.mechanical {
+            continue;
+        } else if door.locked {
+            "Unlock"
+        } else if door.destination.is_some() {
+            "Enter"
+        } else {
+            continue;
+        };
+        entity_commands.insert(Interactable::new(door.half_size.x + INTERACT_REACH, prompt));
+    }
+}
+
+/// Unlocked doors only stay interactable if they lead somewhere.
+fn update_unlocked_interactable(
+    commands: &mut Commands,
+    entity: Entity,
+    door: &Door,
+    interactable: Option<Mut<Interactable>>
+) {
+    match interactable {
+        Some(mut interactable) if door.destination.is_some() => {
+            interactable.prompt = "Enter".to_string();
+        }
+        _ => {
+            commands.entity(entity).remove::<Interactable>();
+        }
+    }
+}
+
+/// Doors unlocked earlier come back open.
+fn show_unlocked_doors(
+    mut commands: Commands,
+    mut door_query: Query<(Entity, &mut Door, &mut Sprite, Option<&mut Interactable>), Added<Opened>>
+) {
+    for (entity, mut door, mut sprite, interactable) in &mut door_query {
+        door.locked = false;
+        commands.entity(entity).remove::<(RigidBody, Collider)>();
+        set_door_open_amount(&mut sprite, door.half_size * 2.0, 1.0);
+        update_unlocked_interactable(&mut commands, entity, &door, interactable);
+    }
+}
+
+fn unlock_doors(
+    mut commands: Commands,
+    mut interactions: EventReader<InteractEvent>,
+    mut inventory_query: Query<&mut Inventory, With<Player>>,
+    mut door_query: Query<(&mut Door, &EntityIid, Option<&mut Interactable>), Without<Opened>>,
+    mut world_state: ResMut<WorldState>,
+    mut inventory_events: EventWriter<InventoryEvent>
+) {
+    for interaction in interactions.iter() {
+        let Ok((mut door, iid, interactable)) = door_query.get_mut(interaction.target) else {
+            continue;
+        };
+        if !door.locked {
+            continue;
+        }
+        let Ok(mut inventory) = inventory_query.get_single_mut() else {
+            continue;
+        };
+
+        let key = Item::Key(door.key);
+        if !inventory.remove(key) {
+            info!("The door is locked");
+            continue;
+        }
+        inventory_events.send(InventoryEvent::Used(key));
+        inventory_events.send(InventoryEvent::Removed(key));
+
+        door.locked = false;
+        world_state.set(iid.as_str(), EntityState::Opened);
+        commands.entity(interaction.target).insert(DoorOpening {
+            timer: Timer::from_seconds(DOOR_OPEN_SECONDS, TimerMode::Once),
+            closing: false,
+        });
+        update_unlocked_interactable(&mut commands, interaction.target, &door, interactable);
+    }
+}
+
+/// Mechanical doors follow their signal, turning around halfway if it flips mid-way.
+#[allow(clippy::type_complexity)]
+fn power_mechanical_doors(
+    mut commands: Commands,
+    mut door_query: Query<
+        (Entity, &Door, &SignalReceiver, Option<&mut DoorOpening>, Option<&Collider>),
+        Changed<SignalReceiver>
+    >
+) {
+    for (entity, door, receiver, opening, collider) in &mut door_query {
+        if !door.mechanical {
+            continue;
+        }
+
+        let closing = !receiver.active;
+        match opening {
+            Some(mut opening) if opening.closing != closing => {
+                let elapsed = opening.timer.duration().saturating_sub(opening.timer.elapsed());
+                opening.timer.set_elapsed(elapsed);
+                opening.closing = closing;
+            }
+            Some(_) => {
+                continue;
+            }
+            // Closed doors are solid, open ones aren't
+            None if collider.is_some() != closing => {
+                commands.entity(entity).insert(DoorOpening {
+                    timer: Timer::from_seconds(DOOR_OPEN_SECONDS, TimerMode::Once),
+                    closing,
+                });
+            }
+            None => {
+                continue;
+            }
+        }
+
+        if closing {
+            commands.entity(entity).remove::<Interactable>();
+        }
+    }
+}
+
+/// The collider only goes away once the door is all the way up, and comes back once it's
+/// all the way down again. A closing door backs up while anything is standing in the doorway,
+/// rather than shutting on top of it.
+fn animate_door_opening(
+    mut commands: Commands,
+    mut door_query: Query<(Entity, &Door, &mut DoorOpening, &mut Sprite, &GlobalTransform)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>
+) {
+    for (entity, door, mut opening, mut sprite, transform) in &mut door_query {
+        let doorway_blocked = opening.closing
+            && rapier_context
+                .intersection_with_shape(
+                    transform.translation().truncate(),
+                    0.0,
+                    &Collider::cuboid(door.half_size.x, door.half_size.y),
+                    QueryFilter::only_dynamic().exclude_sensors()
+                )
+                .is_some();
+        if doorway_blocked {
+            let elapsed = opening.timer.elapsed().saturating_sub(time.delta());
+            opening.timer.set_elapsed(elapsed);
+        } else {
+            opening.timer.tick(time.delta());
+        }
+        let amount = if opening.closing { opening.timer.percent_left() } else { opening.timer.percent() };
+        set_door_open_amount(&mut sprite, door.half_size * 2.0, amount * amount);
+
+        if !opening.timer.finished() {
+            continue;
+        }
+        if opening.closing {
+            commands
+                .entity(entity)
+                .remove::<DoorOpening>()
+                .insert((RigidBody::Fixed, Collider::cuboid(door.half_size.x, door.half_size.y)));
+        } else {
+            commands.entity(entity).remove::<(DoorOpening, RigidBody, Collider)>();
+            if door.mechanical && door.destination.is_some() {
+                commands.entity(entity).insert(Interactable::new(door.half_size.x + INTERACT_REACH, "Enter"));
+            }
+        }
+    }
+}
+
+fn reset_door_transition(
+    mut restarts: EventReader<RestartWorld>,
+    mut transition: ResMut<DoorTransition>,
+    mut overlay_query: Query<&mut BackgroundColor, With<FadeOverlay>>
+) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    *transition = DoorTransition::default();
+    for mut background_color in &mut overlay_query {
+        background_color.0 = Color::NONE;
+    }
+}
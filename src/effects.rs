@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::state::GameState;
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_particles.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_crumbles.run_if(in_state(GameState::Playing)));
+    }
+}
+
+const PARTICLE_GRAVITY: f32 = -600.0;
+
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+/// Sprite that shrinks and fades out, used for things breaking apart.
+#[derive(Component)]
+pub struct Crumble {
+    pub lifetime: Timer,
+}
+
+pub fn spawn_particle_burst(commands: &mut Commands, translation: Vec3, color: Color, count: usize) {
+    for i in 0..count {
+        // Spread evenly upwards with a bit of variation, no need for real randomness here
+        let angle = (i as f32 / count as f32) * TAU * 0.5 + 0.2;
+        let speed = 120.0 + ((i * 37) % 5) as f32 * 30.0;
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(3.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                lifetime: Timer::from_seconds(0.5, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+pub fn spawn_crumble(commands: &mut Commands, translation: Vec3, size: Vec2, color: Color) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        Crumble {
+            lifetime: Timer::from_seconds(0.2, TimerMode::Once),
+        },
+    ));
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>
+) {
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y += PARTICLE_GRAVITY * time.delta_seconds();
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        sprite.color.set_a(particle.lifetime.percent_left());
+    }
+}
+
+fn update_crumbles(
+    mut commands: Commands,
+    mut crumbles: Query<(Entity, &mut Crumble, &mut Transform, &mut Sprite)>,
+    time: Res<Time>
+) {
+    for (entity, mut crumble, mut transform, mut sprite) in &mut crumbles {
+        if crumble.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let left = crumble.lifetime.percent_left();
+        transform.scale = Vec3::splat(left);
+        sprite.color.set_a(left);
+    }
+}
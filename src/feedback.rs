@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::combat::HitEvent;
+use crate::components::{ GroundDetection, Player };
+use crate::state::GameState;
+use crate::systems::RestartWorld;
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>()
+            .init_resource::<HitStop>()
+            .add_systems(Update, shake_on_hit.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, shake_on_landing.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_screen_shake.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_hit_stop.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, reset_feedback);
+    }
+}
+
+/// Trauma based screen shake, the camera reads `offset` and adds it on top of its framing.
+#[derive(Resource)]
+pub struct ScreenShake {
+    /// Between 0 and 1, the shake strength is trauma squared.
+    pub trauma: f32,
+    /// Trauma lost per second.
+    pub decay: f32,
+    pub max_offset: Vec2,
+    pub offset: Vec2,
+}
+
+impl Default for ScreenShake {
+    fn default() -> ScreenShake {
+        ScreenShake {
+            trauma: 0.0,
+            decay: 1.5,
+            max_offset: Vec2::new(8.0, 6.0),
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+}
+
+/// Freezes game time and physics for a moment so hits land with some weight.
+#[derive(Resource, Default)]
+pub struct HitStop {
+    timer: Timer,
+    active: bool,
+}
+
+impl HitStop {
+    /// Starts a freeze, or extends the current one if it's shorter.
+    pub fn freeze(&mut self, seconds: f32) {
+        if !self.active || self.timer.remaining_secs() < seconds {
+            self.timer = Timer::from_seconds(seconds, TimerMode::Once);
+        }
+        self.active = true;
+    }
+}
+
+const HIT_TRAUMA: f32 = 0.15;
+const HIT_STOP_SECONDS: f32 = 0.04;
+const HEAVY_LANDING_SPEED: f32 = 900.0;
+const LANDING_TRAUMA: f32 = 0.35;
+
+/// Hits the player lands or takes, breaking blocks included. `detect_hits` only reports hits
+/// on things that take damage, so swinging at a wall doesn't freeze anything.
+fn shake_on_hit(
+    mut hits: EventReader<HitEvent>,
+    player_query: Query<Entity, With<Player>>,
+    mut shake: ResMut<ScreenShake>,
+    mut hit_stop: ResMut<HitStop>
+) {
+    for hit in hits.iter() {
+        if hit.damage <= 0 || !(player_query.contains(hit.attacker) || player_query.contains(hit.target)) {
+            continue;
+        }
+
+        shake.add_trauma(HIT_TRAUMA * (hit.damage as f32));
+        hit_stop.freeze(HIT_STOP_SECONDS * (hit.damage as f32));
+    }
+}
+
+/// Remembers the fastest fall since leaving the ground and shakes if the landing was hard.
+fn shake_on_landing(
+    player_query: Query<(&Velocity, &GroundDetection), With<Player>>,
+    mut fall_speed: Local<f32>,
+    mut shake: ResMut<ScreenShake>
+) {
+    for (velocity, ground_detection) in &player_query {
+        if !ground_detection.on_ground {
+            *fall_speed = fall_speed.max(-velocity.linvel.y);
+            continue;
+        }
+
+        if *fall_speed > HEAVY_LANDING_SPEED {
+            let excess = (*fall_speed - HEAVY_LANDING_SPEED) / HEAVY_LANDING_SPEED;
+            shake.add_trauma(LANDING_TRAUMA * (1.0 + excess));
+        }
+        *fall_speed = 0.0;
+    }
+}
+
+/// Runs on real time so it keeps decaying through a hit-stop.
+fn update_screen_shake(mut shake: ResMut<ScreenShake>, time: Res<Time>) {
+    shake.trauma = (shake.trauma - shake.decay * time.raw_delta_seconds()).max(0.0);
+
+    // Layered sines are smooth enough to pass for noise and don't need a rng
+    let t = time.raw_elapsed_seconds();
+    let noise = Vec2::new(
+        (t * 43.0).sin() * 0.6 + (t * 71.0).sin() * 0.4,
+        (t * 53.0).cos() * 0.6 + (t * 89.0).sin() * 0.4
+    );
+    shake.offset = shake.max_offset * noise * shake.trauma * shake.trauma;
+}
+
+fn update_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time>,
+    mut rapier_config: ResMut<RapierConfiguration>
+) {
+    if !hit_stop.active {
+        return;
+    }
+
+    if hit_stop.timer.tick(time.raw_delta()).finished() {
+        hit_stop.active = false;
+        time.unpause();
+        rapier_config.physics_pipeline_active = true;
+    } else {
+        // Resuming from the pause menu turns physics back on, keep it off until the freeze is over
+        if !time.is_paused() {
+            time.pause();
+        }
+        rapier_config.physics_pipeline_active = false;
+    }
+}
+
+fn reset_feedback(
+    mut restarts: EventReader<RestartWorld>,
+    mut shake: ResMut<ScreenShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time>
+) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    shake.trauma = 0.0;
+    shake.offset = Vec2::ZERO;
+    if hit_stop.active {
+        *hit_stop = HitStop::default();
+        time.unpause();
+    }
+}
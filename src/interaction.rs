@@ -0,0 +1,258 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components::Player;
+use crate::dialogue::{ dialogue_closed, Dialogue };
+use crate::state::GameState;
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InteractionFocus>()
+            .init_resource::<PromptDevice>()
+            .add_event::<InteractEvent>()
+            .add_systems(Startup, spawn_interact_prompt)
+            .add_systems(Update, spawn_interaction_sensors)
+            .add_systems(Update, despawn_orphaned_sensors)
+            .add_systems(Update, detect_prompt_device)
+            .add_systems(Update, track_interaction_sensors.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_interaction_focus.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_interact_prompt)
+            .add_systems(Update, send_interact_events.run_if(in_state(GameState::Playing).and_then(dialogue_closed)));
+    }
+}
+
+/// Something the player can use by standing next to it and pressing the interact button.
+/// Whatever owns the entity listens for `InteractEvent`s targeting it, and removes this
+/// once there's nothing left to do.
+#[derive(Clone, Debug, Component)]
+pub struct Interactable {
+    /// Radius of the sensor around the entity the player has to touch.
+    pub radius: f32,
+    /// Shown next to the button glyph, e.g. "Open".
+    pub prompt: String,
+}
+
+impl Default for Interactable {
+    fn default() -> Interactable {
+        Interactable {
+            radius: 16.0,
+            prompt: "Use".to_string(),
+        }
+    }
+}
+
+impl Interactable {
+    pub fn new(radius: f32, prompt: impl Into<String>) -> Interactable {
+        Interactable {
+            radius,
+            prompt: prompt.into(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct InteractionSensor {
+    pub interactable: Entity,
+    pub player_inside: bool,
+}
+
+/// Interactable the prompt is shown over and the interact button goes to, the closest one
+/// whose sensor the player is touching.
+#[derive(Resource, Default)]
+pub struct InteractionFocus {
+    pub target: Option<Entity>,
+}
+
+/// Whatever was pressed last decides which glyph the prompt shows.
+#[derive(Resource, Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum PromptDevice {
+    #[default]
+    Keyboard,
+    Gamepad,
+}
+
+impl PromptDevice {
+    fn glyph(self) -> &'static str {
+        match self {
+            PromptDevice::Keyboard => "E",
+            PromptDevice::Gamepad => "X",
+        }
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct InteractEvent {
+    pub target: Entity,
+}
+
+#[derive(Component)]
+pub struct InteractPrompt;
+
+const INTERACT_KEY: KeyCode = KeyCode::E;
+const INTERACT_BUTTON: GamepadButtonType = GamepadButtonType::West;
+const PROMPT_HEIGHT: f32 = 10.0;
+
+fn spawn_interaction_sensors(
+    mut commands: Commands,
+    interactable_query: Query<(Entity, &Interactable), Added<Interactable>>
+) {
+    for (entity, interactable) in &interactable_query {
+        commands.entity(entity).with_children(|builder| {
+            builder
+                .spawn_empty()
+                .insert(ActiveEvents::COLLISION_EVENTS)
+                .insert(Collider::ball(interactable.radius))
+                .insert(Sensor)
+                .insert(TransformBundle::default())
+                .insert(InteractionSensor {
+                    interactable: entity,
+                    player_inside: false,
+                });
+        });
+    }
+}
+
+fn despawn_orphaned_sensors(
+    mut commands: Commands,
+    sensor_query: Query<(Entity, &InteractionSensor)>,
+    interactable_query: Query<(), With<Interactable>>
+) {
+    for (entity, sensor) in &sensor_query {
+        if !interactable_query.contains(sensor.interactable) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn detect_prompt_device(
+    input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut prompt_device: ResMut<PromptDevice>
+) {
+    let device = if gamepad_buttons.get_just_pressed().next().is_some() {
+        PromptDevice::Gamepad
+    } else if input.get_just_pressed().next().is_some() {
+        PromptDevice::Keyboard
+    } else {
+        return;
+    };
+
+    if *prompt_device != device {
+        *prompt_device = device;
+    }
+}
+
+fn track_interaction_sensors(
+    mut collisions: EventReader<CollisionEvent>,
+    mut sensor_query: Query<&mut InteractionSensor>,
+    player_query: Query<(), With<Player>>
+) {
+    for collision_event in collisions.iter() {
+        let (e1, e2, inside) = match collision_event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
+        };
+
+        for (sensor_entity, other) in [(e1, e2), (e2, e1)] {
+            if !player_query.contains(other) {
+                continue;
+            }
+            if let Ok(mut sensor) = sensor_query.get_mut(sensor_entity) {
+                sensor.player_inside = inside;
+            }
+        }
+    }
+}
+
+fn update_interaction_focus(
+    mut focus: ResMut<InteractionFocus>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    sensor_query: Query<(&InteractionSensor, &GlobalTransform)>,
+    interactable_query: Query<(), With<Interactable>>
+) {
+    let target = player_query.get_single().ok().and_then(|player_transform| {
+        let player_translation = player_transform.translation().truncate();
+
+        sensor_query
+            .iter()
+            .filter(|(sensor, _)| sensor.player_inside && interactable_query.contains(sensor.interactable))
+            .map(|(sensor, transform)| {
+                (sensor.interactable, transform.translation().truncate().distance_squared(player_translation))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    });
+
+    if focus.target != target {
+        focus.target = target;
+    }
+}
+
+fn spawn_interact_prompt(mut commands: Commands) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section("", TextStyle {
+                font_size: 12.0,
+                color: Color::WHITE,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        InteractPrompt,
+    ));
+}
+
+/// Floats the prompt just above the focused interactable's sensor, hidden in menus and
+/// conversations.
+fn update_interact_prompt(
+    focus: Res<InteractionFocus>,
+    prompt_device: Res<PromptDevice>,
+    state: Res<State<GameState>>,
+    dialogue: Res<Dialogue>,
+    interactable_query: Query<(&Interactable, &GlobalTransform)>,
+    mut prompt_query: Query<(&mut Text, &mut Transform, &mut Visibility), With<InteractPrompt>>,
+    time: Res<Time>
+) {
+    let Ok((mut text, mut transform, mut visibility)) = prompt_query.get_single_mut() else {
+        return;
+    };
+
+    let shown = *state.get() == GameState::Playing && dialogue.conversation.is_none();
+    let focused = focus.target.and_then(|target| interactable_query.get(target).ok());
+    let Some((interactable, interactable_transform)) = focused.filter(|_| shown) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let label = format!("[{}] {}", prompt_device.glyph(), interactable.prompt);
+    if text.sections[0].value != label {
+        text.sections[0].value = label;
+    }
+
+    let bob = (time.elapsed_seconds() * 4.0).sin() * 1.5;
+    transform.translation = interactable_transform.translation() +
+        Vec3::new(0.0, interactable.radius + PROMPT_HEIGHT + bob, 50.0);
+    *visibility = Visibility::Visible;
+}
+
+fn send_interact_events(
+    input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    focus: Res<InteractionFocus>,
+    mut interactions: EventWriter<InteractEvent>
+) {
+    let button_pressed = gamepads
+        .iter()
+        .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, INTERACT_BUTTON)));
+    if !input.just_pressed(INTERACT_KEY) && !button_pressed {
+        return;
+    }
+
+    if let Some(target) = focus.target {
+        interactions.send(InteractEvent { target });
+    }
+}
@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use serde::{ Deserialize, Serialize };
+
+use crate::components::{ Health, Player, PlayerStats };
+use crate::dialogue::dialogue_closed;
+use crate::effects::spawn_particle_burst;
+use crate::items::{ Item, ItemPickedUp };
+use crate::state::GameState;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InventoryEvent>()
+            .add_systems(Update, store_picked_up_items.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, use_consumables.run_if(in_state(GameState::Playing).and_then(dialogue_closed)))
+            .add_systems(Update, swap_equipment.run_if(in_state(GameState::Playing).and_then(dialogue_closed)))
+            .add_systems(Update, update_player_stats)
+            .add_systems(Update, inventory_feedback.run_if(in_state(GameState::Playing)));
+    }
+}
+
+const MAX_STACK: u32 = 99;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+    Charm,
+}
+
+impl EquipSlot {
+    pub const ALL: [EquipSlot; 3] = [EquipSlot::Weapon, EquipSlot::Armor, EquipSlot::Charm];
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ItemKind {
+    /// Used up with the use key, `heal` is how much health it gives back.
+    Consumable { heal: i32 },
+    Equipment(EquipSlot),
+    /// Only worth collecting.
+    Treasure,
+    /// Used up by unlocking the door with the same id.
+    Key,
+}
+
+/// What a piece of equipment adds on top of the player's base stats.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct StatBonus {
+    pub damage: i32,
+    pub run_speed: f32,
+    pub air_dashes: u32,
+    pub armor: i32,
+}
+
+impl Item {
+    pub fn kind(self) -> ItemKind {
+        match self {
+            Item::Knife => ItemKind::Equipment(EquipSlot::Weapon),
+            Item::Shield => ItemKind::Equipment(EquipSlot::Armor),
+            Item::Boots => ItemKind::Equipment(EquipSlot::Charm),
+            Item::HealingPlant => ItemKind::Consumable { heal: 3 },
+            Item::Meat => ItemKind::Consumable { heal: 2 },
+            Item::Water => ItemKind::Consumable { heal: 1 },
+            Item::Gem => ItemKind::Treasure,
+            Item::Key(_) => ItemKind::Key,
+        }
+    }
+
+    pub fn bonus(self) -> StatBonus {
+        match self {
+            Item::Knife => StatBonus { damage: 1, ..default() },
+            Item::Shield => StatBonus { armor: 1, ..default() },
+            Item::Boots => StatBonus { run_speed: 40.0, air_dashes: 1, ..default() },
+            _ => StatBonus::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    pub weapon: Option<Item>,
+    pub armor: Option<Item>,
+    pub charm: Option<Item>,
+}
+
+impl Equipment {
+    pub fn slot(&self, slot: EquipSlot) -> Option<Item> {
+        match slot {
+            EquipSlot::Weapon => self.weapon,
+            EquipSlot::Armor => self.armor,
+            EquipSlot::Charm => self.charm,
+        }
+    }
+
+    fn slot_mut(&mut self, slot: EquipSlot) -> &mut Option<Item> {
+        match slot {
+            EquipSlot::Weapon => &mut self.weapon,
+            EquipSlot::Armor => &mut self.armor,
+            EquipSlot::Charm => &mut self.charm,
+        }
+    }
+}
+
+/// Everything the player carries. Part of the save file.
+#[derive(Clone, Debug, Default, Component, Serialize, Deserialize)]
+pub struct Inventory {
+    /// In the order they were first picked up.
+    pub stacks: Vec<ItemStack>,
+    pub equipment: Equipment,
+}
+
+impl Inventory {
+    /// Equipment goes straight into its slot if that's free, everything else gets stacked.
+    /// Returns false if there was no room for it.
+    pub fn add(&mut self, item: Item) -> bool {
+        if let ItemKind::Equipment(slot) = item.kind() {
+            let equipped = self.equipment.slot_mut(slot);
+            if equipped.is_none() {
+                *equipped = Some(item);
+                return true;
+            }
+        }
+
+        match self.stacks.iter_mut().find(|stack| stack.item == item) {
+            Some(stack) if stack.count >= MAX_STACK => false,
+            Some(stack) => {
+                stack.count += 1;
+                true
+            }
+            None => {
+                self.stacks.push(ItemStack { item, count: 1 });
+                true
+            }
+        }
+    }
+
+    /// Moves a stacked piece of equipment into its slot, whatever was in there goes back
+    /// into the stacks. Returns false if `item` isn't stacked equipment.
+    pub fn equip(&mut self, item: Item) -> bool {
+        let ItemKind::Equipment(slot) = item.kind() else {
+            return false;
+        };
+        if !self.remove(item) {
+            return false;
+        }
+
+        if let Some(unequipped) = self.equipment.slot_mut(slot).replace(item) {
+            self.add(unequipped);
+        }
+        true
+    }
+
+    /// Takes one `item` out of the stacks, returns false if there wasn't any.
+    pub fn remove(&mut self, item: Item) -> bool {
+        let Some(index) = self.stacks.iter().position(|stack| stack.item == item) else {
+            return false;
+        };
+
+        self.stacks[index].count -= 1;
+        if self.stacks[index].count == 0 {
+            self.stacks.remove(index);
+        }
+        true
+    }
+
+    pub fn bonus(&self) -> StatBonus {
+        EquipSlot::ALL
+            .iter()
+            .filter_map(|slot| self.equipment.slot(*slot))
+            .map(Item::bonus)
+            .fold(StatBonus::default(), |total, bonus| StatBonus {
+                damage: total.damage + bonus.damage,
+                run_speed: total.run_speed + bonus.run_speed,
+                air_dashes: total.air_dashes + bonus.air_dashes,
+                armor: total.armor + bonus.armor,
+            })
+    }
+}
+
+/// Changes to the player's inventory, for anything that shows or reacts to them.
+#[derive(Event, Copy, Clone, Debug)]
+pub enum InventoryEvent {
+    Added(Item),
+    Removed(Item),
+    Used(Item),
+}
+
+fn store_picked_up_items(
+    mut picked_up: EventReader<ItemPickedUp>,
+    mut inventory_query: Query<&mut Inventory, With<Player>>,
+    mut inventory_events: EventWriter<InventoryEvent>
+) {
+    let Ok(mut inventory) = inventory_query.get_single_mut() else {
+        return;
+    };
+
+    for pickup in picked_up.iter() {
+        if inventory.add(pickup.item) {
+            inventory_events.send(InventoryEvent::Added(pickup.item));
+        } else {
+            warn!("No room for another {:?}", pickup.item);
+        }
+    }
+}
+
+/// Eats the first consumable in the inventory, unless the player is already at full health.
+fn use_consumables(
+    input: Res<Input<KeyCode>>,
+    mut player_query: Query<(&mut Inventory, &mut Health), With<Player>>,
+    mut inventory_events: EventWriter<InventoryEvent>
+) {
+    if !input.just_pressed(KeyCode::Q) {
+        return;
+    }
+    let Ok((mut inventory, mut health)) = player_query.get_single_mut() else {
+        return;
+    };
+    if health.current >= health.max {
+        return;
+    }
+
+    let consumable = inventory.stacks.iter().find_map(|stack| {
+        match stack.item.kind() {
+            ItemKind::Consumable { heal } => Some((stack.item, heal)),
+            _ => None,
+        }
+    });
+    let Some((item, heal)) = consumable else {
+        return;
+    };
+
+    inventory.remove(item);
+    health.current = (health.current + heal).min(health.max);
+    inventory_events.send(InventoryEvent::Used(item));
+    inventory_events.send(InventoryEvent::Removed(item));
+}
+
+/// Swaps the first spare piece of equipment with the one in its slot.
+fn swap_equipment(
+    input: Res<Input<KeyCode>>,
+    mut inventory_query: Query<&mut Inventory, With<Player>>
+) {
+    if !input.just_pressed(KeyCode::R) {
+        return;
+    }
+    let Ok(mut inventory) = inventory_query.get_single_mut() else {
+        return;
+    };
+
+    let spare = inventory.stacks
+        .iter()
+        .map(|stack| stack.item)
+        .find(|item| matches!(item.kind(), ItemKind::Equipment(_)));
+    if let Some(item) = spare {
+        inventory.equip(item);
+    }
+}
+
+fn update_player_stats(mut player_query: Query<(&Inventory, &mut PlayerStats), Changed<Inventory>>) {
+    for (inventory, mut stats) in &mut player_query {
+        let bonus = inventory.bonus();
+        let base = PlayerStats::default();
+
+        *stats = PlayerStats {
+            damage_bonus: base.damage_bonus + bonus.damage,
+            run_speed: base.run_speed + bonus.run_speed,
+            air_dashes: base.air_dashes + bonus.air_dashes,
+            armor: base.armor + bonus.armor,
+            ..base
+        };
+    }
+}
+
+fn inventory_feedback(
+    mut commands: Commands,
+    mut inventory_events: EventReader<InventoryEvent>,
+    player_query: Query<&GlobalTransform, With<Player>>
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    for event in inventory_events.iter() {
+        match event {
+            InventoryEvent::Used(item) => {
+                spawn_particle_burst(&mut commands, player_transform.translation(), item.color(), 10);
+            }
+            InventoryEvent::Added(item) => debug!("Added {:?} to the inventory", item),
+            InventoryEvent::Removed(item) => debug!("Removed {:?} from the inventory", item),
+        }
+    }
+}
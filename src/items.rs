@@ -0,0 +1,327 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{ Deserialize, Serialize };
+use std::f32::consts::PI;
+
+use crate::components::{ Chest, ColliderBundle, ItemPickup, Player, CHEST_OPEN_COLOR };
+use crate::destructible::BlockBrokenEvent;
+use crate::effects::spawn_particle_burst;
+use crate::interaction::{ InteractEvent, Interactable };
+use crate::state::GameState;
+use crate::world_state::{ EntityState, Opened, WorldState };
+
+pub struct ItemsPlugin;
+
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ItemPickedUp>()
+            .add_systems(Update, open_chests.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, show_opened_chests)
+            .add_systems(Update, animate_chest_opening.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, drop_block_loot.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, collect_items.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Mirrors the `Item` enum in the LDtk project.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum Item {
+    Knife,
+    HealingPlant,
+    Meat,
+    Boots,
+    Water,
+    Gem,
+    /// Opens the locked doors with the same `key` id.
+    Key(u32),
+    Shield,
+}
+
+impl Item {
+    pub fn from_ldtk(value: &str) -> Option<Item> {
+        match value {
+            "Knife" => Some(Item::Knife),
+            "Healing_Plant" => Some(Item::HealingPlant),
+            "Meat" => Some(Item::Meat),
+            "Boots" => Some(Item::Boots),
+            "Water" => Some(Item::Water),
+            "Gem" => Some(Item::Gem),
+            "Key" => Some(Item::Key(0)),
+            "Shield" => Some(Item::Shield),
+            _ => None,
+        }
+    }
+
+    /// Same colors as the enum values in LDtk, until there's art for them.
+    pub fn color(self) -> Color {
+        match self {
+            Item::Knife => Color::rgb(0.74, 0.65, 0.64),
+            Item::HealingPlant => Color::rgb(0.51, 0.66, 0.25),
+            Item::Meat => Color::rgb(0.79, 0.44, 0.31),
+            Item::Boots => Color::rgb(0.53, 0.44, 0.42),
+            Item::Water => Color::rgb(0.47, 0.57, 0.71),
+            Item::Gem => Color::rgb(0.9, 0.42, 0.22),
+            Item::Key(_) => Color::rgb(0.92, 0.82, 0.35),
+            Item::Shield => Color::rgb(0.45, 0.52, 0.6),
+        }
+    }
+
+    /// Keys get the id from the entity's `key` field, LDtk enums can't carry one.
+    pub fn with_key(self, key: u32) -> Item {
+        match self {
+            Item::Key(_) => Item::Key(key),
+            item => item,
+        }
+    }
+}
+
+/// Reads the `key` field given to keys placed on or in an entity.
+pub fn key_field(entity_instance: &EntityInstance) -> u32 {
+    entity_instance
+        .get_int_field("key")
+        .map(|key| *key as u32)
+        .unwrap_or(0)
+}
+
+/// Reads an `Array<LocalEnum.Item>` field, skipping values this build doesn't know about.
+pub fn items_field(fields: &impl LdtkFields, identifier: &str) -> Vec<Item> {
+    let Ok(values) = fields.get_maybe_enums_field(identifier) else {
+        return Vec::new();
+    };
+
+    values
+        .iter()
+        .flatten()
+        .filter_map(|value| {
+            let item = Item::from_ldtk(value);
+            if item.is_none() {
+                warn!("Unknown item {} in {}", value, identifier);
+            }
+            item
+        })
+        .collect()
+}
+
+/// One in `LOOT_ODDS` rolls drops something.
+const LOOT_ODDS: u64 = 2;
+
+/// 64-bit FNV-1a, unlike std's `DefaultHasher` it's guaranteed to give the same result on
+/// every platform and toolchain.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ (*byte as u64)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Picks from a level's loot table. Seeded from what broke so the same block always drops
+/// the same thing, there's no rng in the project and reloading shouldn't reroll loot.
+pub fn roll_loot(table: &[Item], seed: &[u8]) -> Option<Item> {
+    if table.is_empty() {
+        return None;
+    }
+
+    let roll = fnv1a(seed);
+
+    (roll % LOOT_ODDS == 0).then(|| table[((roll / LOOT_ODDS) % (table.len() as u64)) as usize])
+}
+
+/// Sent when the player collects an item.
+#[derive(Event, Clone, Debug)]
+pub struct ItemPickedUp {
+    pub item: Item,
+}
+
+const ITEM_HALF_SIZE: f32 = 4.0;
+const DROP_PICKUP_DELAY: f32 = 0.4;
+
+pub fn item_collider() -> ColliderBundle {
+    ColliderBundle {
+        collider: Collider::cuboid(ITEM_HALF_SIZE, ITEM_HALF_SIZE),
+        rigid_body: RigidBody::Dynamic,
+        rotation_constraints: LockedAxes::ROTATION_LOCKED,
+        friction: Friction::coefficient(0.8),
+        ..Default::default()
+    }
+}
+
+pub fn item_sprite_bundle(item: Item) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: item.color(),
+            custom_size: Some(Vec2::splat(ITEM_HALF_SIZE * 2.0)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Spawns an item flying out of something as a child of `level`, so it goes away with it.
+pub fn spawn_item_drop(commands: &mut Commands, level: Entity, item: Item, translation: Vec3, linvel: Vec2) -> Entity {
+    let mut collider_bundle = item_collider();
+    collider_bundle.velocity = Velocity::linear(linvel);
+
+    let drop = commands
+        .spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(translation),
+                ..item_sprite_bundle(item)
+            },
+            collider_bundle,
+            ItemPickup::new(item, DROP_PICKUP_DELAY),
+        ))
+        .id();
+    commands.entity(level).add_child(drop);
+    drop
+}
+
+/// Stands in for an LDtk iid on the things a chest drops, so picking them up is remembered
+/// the same way as for placed items.
+fn chest_drop_iid(chest_iid: &str, index: usize) -> String {
+    format!("{}/content/{}", chest_iid, index)
+}
+
+#[derive(Component)]
+pub struct ChestOpening {
+    pub timer: Timer,
+}
+
+/// The content is spilled by `show_opened_chests`, same as for chests coming back open.
+fn open_chests(
+    mut commands: Commands,
+    mut interactions: EventReader<InteractEvent>,
+    chest_query: Query<(&Chest, &EntityIid, &GlobalTransform), Without<Opened>>,
+    mut world_state: ResMut<WorldState>
+) {
+    for interaction in interactions.iter() {
+        let Ok((_, iid, transform)) = chest_query.get(interaction.target) else {
+            continue;
+        };
+
+        world_state.set(iid.as_str(), EntityState::Opened);
+        commands.entity(interaction.target).insert((
+            Opened,
+            ChestOpening {
+                timer: Timer::from_seconds(0.3, TimerMode::Once),
+            },
+        ));
+        spawn_particle_burst(&mut commands, transform.translation(), CHEST_OPEN_COLOR, 8);
+    }
+}
+
+/// Opened chests, fresh or coming back open, spill whatever in them hasn't been picked up yet.
+/// Goes by the local transforms, the chest may have only just been spawned.
+#[allow(clippy::type_complexity)]
+fn show_opened_chests(
+    mut commands: Commands,
+    mut chest_query: Query<(Entity, &Chest, &EntityIid, &Transform, &Parent, &mut Sprite), Added<Opened>>,
+    layer_query: Query<(&Parent, &Transform), With<LayerMetadata>>,
+    world_state: Res<WorldState>
+) {
+    for (entity, chest, iid, transform, parent, mut sprite) in &mut chest_query {
+        sprite.color = CHEST_OPEN_COLOR;
+        commands.entity(entity).remove::<Interactable>();
+
+        let Ok((level, layer_transform)) = layer_query.get(parent.get()) else {
+            continue;
+        };
+
+        // Fan the content out upwards, centered over the chest
+        let translation = layer_transform.translation + transform.translation;
+        let spread = (chest.content.len() as f32 - 1.0) / 2.0;
+        for (i, item) in chest.content.iter().enumerate() {
+            let drop_iid = chest_drop_iid(iid.as_str(), i);
+            if world_state.get(&drop_iid) == Some(EntityState::Removed) {
+                continue;
+            }
+
+            let linvel = Vec2::new((i as f32 - spread) * 60.0, 320.0);
+            let drop = spawn_item_drop(&mut commands, level.get(), *item, translation, linvel);
+            commands.entity(drop).insert(EntityIid::new(drop_iid));
+        }
+    }
+}
+
+/// Squashes down and pops back up as the lid flies open.
+fn animate_chest_opening(
+    mut commands: Commands,
+    mut opening_query: Query<(Entity, &mut ChestOpening, &mut Transform)>,
+    time: Res<Time>
+) {
+    for (entity, mut opening, mut transform) in &mut opening_query {
+        if opening.timer.tick(time.delta()).finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<ChestOpening>();
+            continue;
+        }
+
+        let squash = 0.25 * (opening.timer.percent() * PI).sin();
+        transform.scale = Vec3::new(1.0 + squash, 1.0 - squash, 1.0);
+    }
+}
+
+/// Breakable blocks roll on their level's `Loot` table.
+fn drop_block_loot(
+    mut commands: Commands,
+    mut broken_blocks: EventReader<BlockBrokenEvent>,
+    level_query: Query<(&Handle<LdtkLevel>, &GlobalTransform)>,
+    ldtk_levels: Res<Assets<LdtkLevel>>
+) {
+    for broken in broken_blocks.iter() {
+        let Ok((level_handle, level_transform)) = level_query.get(broken.level) else {
+            continue;
+        };
+        let Some(ldtk_level) = ldtk_levels.get(level_handle) else {
+            continue;
+        };
+
+        let table = items_field(&ldtk_level.level, "Loot");
+        let seed = [
+            ldtk_level.level.iid.as_bytes(),
+            &broken.grid_coords.x.to_le_bytes(),
+            &broken.grid_coords.y.to_le_bytes(),
+        ].concat();
+        if let Some(item) = roll_loot(&table, &seed) {
+            let translation = broken.translation - level_transform.translation();
+            spawn_item_drop(&mut commands, broken.level, item, translation, Vec2::new(0.0, 200.0));
+        }
+    }
+}
+
+/// Items placed in LDtk and chest content stay collected, other drops just disappear.
+fn collect_items(
+    mut commands: Commands,
+    player_query: Query<(&GlobalTransform, &Collider), With<Player>>,
+    mut item_query: Query<(Entity, &mut ItemPickup, &GlobalTransform, Option<&EntityIid>)>,
+    mut world_state: ResMut<WorldState>,
+    mut picked_up: EventWriter<ItemPickedUp>,
+    time: Res<Time>
+) {
+    let Ok((player_transform, player_collider)) = player_query.get_single() else {
+        return;
+    };
+    let player_translation = player_transform.translation().truncate();
+    let player_half_size = player_collider
+        .as_cuboid()
+        .map(|cuboid| cuboid.half_extents())
+        .unwrap_or_default();
+
+    for (entity, mut pickup, transform, iid) in &mut item_query {
+        if !pickup.delay.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let offset = player_translation - transform.translation().truncate();
+        let reach = player_half_size + ITEM_HALF_SIZE;
+        if offset.x.abs() > reach.x || offset.y.abs() > reach.y {
+            continue;
+        }
+
+        if let Some(iid) = iid {
+            world_state.set(iid.as_str(), EntityState::Removed);
+        }
+        spawn_particle_burst(&mut commands, transform.translation(), pickup.item.color(), 6);
+        picked_up.send(ItemPickedUp { item: pickup.item });
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy::asset::{ HandleId, LoadState };
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::HashSet;
+
+use crate::animation::AnimationResource;
+use crate::menu::{ despawn_screen, menu_root, spawn_menu_title };
+use crate::state::GameState;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Loading), spawn_loading_screen)
+            .add_systems(Update, track_loading.run_if(in_state(GameState::Loading)))
+            .add_systems(OnExit(GameState::Loading), despawn_screen::<LoadingScreen>);
+    }
+}
+
+#[derive(Component)]
+pub struct LoadingScreen;
+
+#[derive(Component)]
+pub struct LoadingBar;
+
+#[derive(Component)]
+pub struct LoadingMessage;
+
+const BAR_WIDTH: f32 = 320.0;
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((menu_root(), LoadingScreen))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Loading");
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(16.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.8, 0.7, 0.3).into(),
+                            ..default()
+                        },
+                        LoadingBar,
+                    ));
+                });
+
+            parent.spawn((
+                TextBundle::from_section("", TextStyle {
+                    font_size: 18.0,
+                    color: Color::rgb(0.9, 0.3, 0.3),
+                    ..default()
+                }),
+                LoadingMessage,
+            ));
+        });
+}
+
+/// Waits on the LDtk project, its tilesets and levels, and every animation sheet.
+/// Anything that fails keeps the game on the loading screen with the paths listed.
+#[allow(clippy::too_many_arguments)]
+fn track_loading(
+    asset_server: Res<AssetServer>,
+    animations: Res<AnimationResource>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    world_query: Query<&Handle<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut reported: Local<HashSet<HandleId>>,
+    mut bar_query: Query<&mut Style, With<LoadingBar>>,
+    mut message_query: Query<&mut Text, With<LoadingMessage>>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    let mut handles: Vec<HandleUntyped> = animations
+        .sheets(&texture_atlases)
+        .map(|sheet| sheet.clone_untyped())
+        .collect();
+
+    for world_handle in &world_query {
+        handles.push(world_handle.clone_untyped());
+
+        // The tilesets and levels are only known once the project itself is in
+        if let Some(ldtk_asset) = ldtk_assets.get(world_handle) {
+            handles.extend(ldtk_asset.tileset_map.values().map(|tileset| tileset.clone_untyped()));
+            handles.extend(ldtk_asset.level_map.values().map(|level| level.clone_untyped()));
+        }
+    }
+
+    let mut loaded = 0;
+    let mut failed = Vec::new();
+    for handle in &handles {
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => {
+                loaded += 1;
+            }
+            LoadState::Failed => {
+                let path = asset_server
+                    .get_handle_path(handle)
+                    .map_or_else(|| format!("{:?}", handle.id()), |path| path.path().display().to_string());
+                if reported.insert(handle.id()) {
+                    error!("Failed to load {}", path);
+                }
+                failed.push(path);
+            }
+            _ => {}
+        }
+    }
+
+    let progress = if handles.is_empty() { 0.0 } else { (loaded as f32) / (handles.len() as f32) };
+    for mut style in &mut bar_query {
+        style.width = Val::Percent(progress * 100.0);
+    }
+
+    if !failed.is_empty() {
+        for mut text in &mut message_query {
+            text.sections[0].value = format!("Failed to load:\n{}", failed.join("\n"));
+        }
+        return;
+    }
+
+    if !world_query.is_empty() && loaded == handles.len() {
+        next_state.set(GameState::MainMenu);
+    }
+}
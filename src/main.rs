@@ -3,41 +3,78 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 mod animation;
+mod checkpoints;
+mod combat;
 mod components;
 mod collisions;
 mod debug;
+mod destructible;
+mod dialogue;
+mod doors;
+mod effects;
+mod feedback;
+mod interaction;
+mod inventory;
+mod items;
+mod loading;
+mod mechanisms;
+mod menu;
+mod pixel_perfect;
 mod player;
+mod save;
+mod settings;
+mod signals;
+mod state;
 mod systems;
+mod world_state;
 
 // use input::PlayerInput;
 use debug::DebugPlugin;
 use animation::AnimationPlugin;
+use checkpoints::CheckpointPlugin;
+use combat::CombatPlugin;
+use destructible::DestructiblePlugin;
+use dialogue::DialoguePlugin;
+use doors::DoorPlugin;
+use effects::EffectsPlugin;
+use feedback::FeedbackPlugin;
+use interaction::InteractionPlugin;
+use inventory::InventoryPlugin;
+use items::ItemsPlugin;
+use pixel_perfect::PixelPerfectPlugin;
+use loading::LoadingPlugin;
+use mechanisms::MechanismPlugin;
+use menu::MenuPlugin;
+use save::SavePlugin;
+use settings::{ DisplaySettings, SettingsPlugin };
+use signals::SignalPlugin;
+use state::{ GameState, GameStatePlugin };
+use world_state::WorldStatePlugin;
 // use animation::PlayerPlugin;
 //remember movement and stuff
 
 fn main() {
+    let display_settings = DisplaySettings::load();
+
     App::new()
-        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
-        // .add_plugins(
-        //     DefaultPlugins.set(ImagePlugin::default_nearest())
-        //         .set(WindowPlugin {
-        //             primary_window: Some(Window {
-        //                 title: "Dungeon-Madness".into(),
-        //                 resolution: (640.0, 480.0).into(),
-        //                 resizable: false,
-        //                 ..default()
-        //             }),
-        //             ..default()
-        //         })
-        //         .build()
-        // )
-        .add_plugins((LdtkPlugin, RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0)))
+        .add_plugins(
+            DefaultPlugins.set(ImagePlugin::default_nearest()).set(WindowPlugin {
+                primary_window: Some(display_settings.window()),
+                ..default()
+            })
+        )
+        .insert_resource(display_settings)
+        .add_plugins((LdtkPlugin, RapierPhysicsPlugin::<collisions::OneWayPlatformHooks>::pixels_per_meter(100.0)))
         // .add_plugins(PlayerInput)
         .add_plugins(DebugPlugin)
         .add_plugins(AnimationPlugin)
+        .add_plugins((GameStatePlugin, LoadingPlugin, MenuPlugin, PixelPerfectPlugin, SavePlugin, SettingsPlugin, WorldStatePlugin))
+        .add_plugins((CheckpointPlugin, CombatPlugin, DestructiblePlugin, DialoguePlugin, DoorPlugin, EffectsPlugin, FeedbackPlugin, InteractionPlugin, InventoryPlugin, ItemsPlugin, MechanismPlugin, SignalPlugin))
         // .add_plugins(AnimatorPlugin)
         // .add_plugins(PlayerPlugin)
-        .insert_resource(LevelSelection::Uid(0))
+        .insert_resource(LevelSelection::Uid(systems::START_LEVEL_UID))
+        .init_resource::<systems::CameraLevel>()
+        .init_resource::<systems::CameraController>()
         .insert_resource(LdtkSettings {
             level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
                 load_level_neighbors: true,
@@ -49,15 +86,41 @@ fn main() {
             gravity: Vec2::new(0.0, -2000.0),
             ..Default::default()
         })
+        .add_event::<systems::RestartWorld>()
         .add_systems(Startup, systems::setup)
-        .add_systems(Update, collisions::spawn_wall_collision)
+        .add_systems(Update, systems::restart_world)
+        .add_systems(Update, collisions::spawn_merged_collision::<components::Wall>.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::spawn_merged_collision::<components::Platform>.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::spawn_merged_collision::<components::Breakable>.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::spawn_merged_collision::<components::Hazard>.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::spawn_slope_collision.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::show_slopes.run_if(in_state(GameState::Playing)))
         // .add_systems(Update, systems::movement)
-        .add_systems(Update, systems::camera_fit_inside_current_level)
-        .add_systems(Update, player::player_movement)
-        .add_systems(Update, collisions::spawn_ground_sensor)
-        .add_systems(Update, collisions::ground_detection)
-        .add_systems(Update, collisions::update_on_ground)
+        .add_systems(Update, systems::camera_fit_inside_current_level.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, player::player_movement.run_if(in_state(GameState::Playing).and_then(dialogue::dialogue_closed)))
+        .add_systems(Update, collisions::spawn_ground_sensor.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::ground_detection.run_if(in_state(GameState::Playing)))
+        .add_systems(Update, collisions::update_on_ground.run_if(in_state(GameState::Playing)))
         .register_ldtk_int_cell::<components::WallBundle>(1)
+        .register_ldtk_int_cell::<components::SlopeBundle>(2)
+        .register_ldtk_int_cell::<components::SlopeBundle>(3)
+        .register_ldtk_int_cell::<components::SlopeBundle>(4)
+        .register_ldtk_int_cell::<components::SlopeBundle>(5)
+        .register_ldtk_int_cell::<components::SlopeBundle>(6)
+        .register_ldtk_int_cell::<components::SlopeBundle>(7)
+        .register_ldtk_int_cell::<components::PlatformBundle>(8)
+        .register_ldtk_int_cell::<components::BreakableBundle>(9)
+        .register_ldtk_int_cell::<components::HazardBundle>(10)
         .register_ldtk_entity::<components::PlayerBundle>("Player")
+        .register_ldtk_entity::<components::DoorBundle>("Door")
+        .register_ldtk_entity::<components::CheckpointBundle>("Checkpoint")
+        .register_ldtk_entity::<components::ChestBundle>("Chest")
+        .register_ldtk_entity::<components::ItemBundle>("Item")
+        .register_ldtk_entity::<components::NpcBundle>("NPC")
+        .register_ldtk_entity::<components::LeverBundle>("Lever")
+        .register_ldtk_entity::<components::PressurePlateBundle>("PressurePlate")
+        .register_ldtk_entity::<components::TriggerVolumeBundle>("TriggerVolume")
+        .register_ldtk_entity::<components::MovingPlatformBundle>("MovingPlatform")
+        .register_ldtk_entity::<components::SpawnerBundle>("Spawner")
         .run();
 }
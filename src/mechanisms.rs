@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components::{ GroundSensor, MovingPlatform, Spawner };
+use crate::dialogue::dialogue_closed;
+use crate::effects::spawn_particle_burst;
+use crate::items::spawn_item_drop;
+use crate::player::player_movement;
+use crate::signals::SignalReceiver;
+use crate::state::GameState;
+
+pub struct MechanismPlugin;
+
+impl Plugin for MechanismPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (setup_moving_platforms, move_platforms).chain().run_if(in_state(GameState::Playing)))
+            // After the player's movement, which sets their speed from scratch
+            .add_systems(
+                Update,
+                carry_riders.after(player_movement).run_if(in_state(GameState::Playing).and_then(dialogue_closed))
+            )
+            .add_systems(Update, run_spawners.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Turns the platform's `end` point into a translation next to its own.
+fn setup_moving_platforms(
+    mut platform_query: Query<(&mut MovingPlatform, &Transform, &Parent), Added<MovingPlatform>>,
+    layer_query: Query<&LayerMetadata>
+) {
+    for (mut platform, transform, parent) in &mut platform_query {
+        let Ok(layer) = layer_query.get(parent.get()) else {
+            continue;
+        };
+
+        let grid_size = layer.grid_size as f32;
+        platform.start = transform.translation.truncate();
+        platform.end = platform.start + Vec2::new(platform.travel.x as f32, -platform.travel.y as f32) * grid_size;
+    }
+}
+
+/// Kinematic bodies driven by velocity, so whatever stands on them gets pushed along.
+fn move_platforms(
+    mut platform_query: Query<(&MovingPlatform, &SignalReceiver, &Transform, &mut Velocity)>,
+    time: Res<Time>
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (platform, receiver, transform, mut velocity) in &mut platform_query {
+        let target = if receiver.active { platform.end } else { platform.start };
+        let linvel = ((target - transform.translation.truncate()) / delta).clamp_length_max(platform.speed);
+
+        if velocity.linvel != linvel {
+            velocity.linvel = linvel;
+        }
+    }
+}
+
+/// Adds the platform's horizontal speed to whoever is standing on it.
+fn carry_riders(
+    sensor_query: Query<&GroundSensor>,
+    platform_query: Query<&Velocity, With<MovingPlatform>>,
+    mut rider_query: Query<&mut Velocity, Without<MovingPlatform>>
+) {
+    for sensor in &sensor_query {
+        let carried = sensor.intersecting_ground_entities
+            .iter()
+            .find_map(|entity| platform_query.get(*entity).ok());
+        let Some(platform_velocity) = carried else {
+            continue;
+        };
+
+        if let Ok(mut velocity) = rider_query.get_mut(sensor.ground_detection_entity) {
+            velocity.linvel.x += platform_velocity.linvel.x;
+        }
+    }
+}
+
+fn run_spawners(
+    mut commands: Commands,
+    spawner_query: Query<(&Spawner, &SignalReceiver, &GlobalTransform, &Parent), Changed<SignalReceiver>>,
+    layer_query: Query<&Parent, With<LayerMetadata>>,
+    level_query: Query<&GlobalTransform>
+) {
+    for (spawner, receiver, transform, parent) in &spawner_query {
+        if !receiver.active {
+            continue;
+        }
+        let Some(item) = spawner.item else {
+            continue;
+        };
+        let Ok(level) = layer_query.get(parent.get()).map(|layer_parent| layer_parent.get()) else {
+            continue;
+        };
+        let Ok(level_transform) = level_query.get(level) else {
+            continue;
+        };
+
+        spawn_particle_burst(&mut commands, transform.translation(), item.color(), 6);
+        let translation = transform.translation() - level_transform.translation();
+        spawn_item_drop(&mut commands, level, item, translation, Vec2::new(0.0, 120.0));
+    }
+}
@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+
+use crate::save::{ LoadMenu, RestartLevel, SaveGame, SaveSlots };
+use crate::settings::OptionsMenu;
+use crate::state::GameState;
+use crate::systems::RestartWorld;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, highlight_buttons)
+            .add_systems(Update, menu_buttons)
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnExit(GameState::MainMenu), despawn_screen::<MainMenuRoot>)
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+            .add_systems(OnExit(GameState::Paused), despawn_screen::<PauseMenuRoot>)
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over)
+            .add_systems(OnExit(GameState::GameOver), despawn_screen::<GameOverRoot>);
+    }
+}
+
+pub const MENU_BACKGROUND: Color = Color::rgba(0.05, 0.05, 0.08, 0.9);
+pub const BUTTON_COLOR: Color = Color::rgb(0.2, 0.2, 0.25);
+pub const BUTTON_HOVER_COLOR: Color = Color::rgb(0.3, 0.3, 0.38);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub enum MenuButton {
+    NewGame,
+    LoadGame,
+    Resume,
+    SaveGame,
+    RestartLevel,
+    TryAgain,
+    Options,
+    MainMenu,
+    Quit,
+}
+
+#[derive(Component)]
+pub struct MainMenuRoot;
+
+#[derive(Component)]
+pub struct PauseMenuRoot;
+
+#[derive(Component)]
+pub struct GameOverRoot;
+
+/// Full screen column of buttons, used by every menu.
+pub fn menu_root() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(8.0),
+            ..default()
+        },
+        background_color: MENU_BACKGROUND.into(),
+        z_index: ZIndex::Global(50),
+        ..default()
+    }
+}
+
+pub fn spawn_menu_title(parent: &mut ChildBuilder, title: impl Into<String>) {
+    parent.spawn(
+        TextBundle::from_section(title, TextStyle {
+            font_size: 48.0,
+            color: Color::WHITE,
+            ..default()
+        }).with_style(Style {
+            margin: UiRect::bottom(Val::Px(24.0)),
+            ..default()
+        })
+    );
+}
+
+pub fn spawn_menu_button(parent: &mut ChildBuilder, label: impl Into<String>, button: impl Component) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(260.0),
+                    height: Val::Px(40.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|button| {
+            button.spawn(
+                TextBundle::from_section(label, TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                })
+            );
+        });
+}
+
+pub fn despawn_screen<T: Component>(mut commands: Commands, root_query: Query<Entity, With<T>>) {
+    for entity in &root_query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn highlight_buttons(
+    mut button_query: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>)>
+) {
+    for (interaction, mut background_color) in &mut button_query {
+        *background_color = (match interaction {
+            Interaction::Hovered | Interaction::Pressed => BUTTON_HOVER_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }).into();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn menu_buttons(
+    button_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut options_menu: ResMut<OptionsMenu>,
+    mut load_menu: ResMut<LoadMenu>,
+    mut save_slots: ResMut<SaveSlots>,
+    mut restarts: EventWriter<RestartWorld>,
+    mut level_restarts: EventWriter<RestartLevel>,
+    mut saves: EventWriter<SaveGame>,
+    mut exit: EventWriter<AppExit>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            MenuButton::NewGame => {
+                match SaveSlots::first_free() {
+                    Some(slot) => {
+                        save_slots.active = slot;
+                        restarts.send(RestartWorld);
+                        next_state.set(GameState::Playing);
+                    }
+                    // Every slot is taken, let the player pick which one to give up
+                    None => {
+                        load_menu.open = true;
+                        load_menu.overwrite = true;
+                    }
+                }
+            }
+            MenuButton::LoadGame => {
+                load_menu.open = true;
+                load_menu.overwrite = false;
+            }
+            MenuButton::Resume => next_state.set(GameState::Playing),
+            MenuButton::SaveGame => saves.send(SaveGame),
+            MenuButton::RestartLevel => level_restarts.send(RestartLevel),
+            MenuButton::TryAgain => {
+                restarts.send(RestartWorld);
+                next_state.set(GameState::Playing);
+            }
+            MenuButton::Options => {
+                options_menu.open = true;
+            }
+            // Leaving the run behind, so New Game starts over
+            MenuButton::MainMenu => {
+                restarts.send(RestartWorld);
+                next_state.set(GameState::MainMenu);
+            }
+            MenuButton::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    commands
+        .spawn((menu_root(), MainMenuRoot))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Dungeon Madness");
+            spawn_menu_button(parent, "New Game", MenuButton::NewGame);
+            spawn_menu_button(parent, "Load Game", MenuButton::LoadGame);
+            spawn_menu_button(parent, "Options", MenuButton::Options);
+            spawn_menu_button(parent, "Quit", MenuButton::Quit);
+        });
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    // See-through, so the frozen game shows behind it
+    let root = NodeBundle {
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+        ..menu_root()
+    };
+    commands
+        .spawn((root, PauseMenuRoot))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Paused");
+            spawn_menu_button(parent, "Resume", MenuButton::Resume);
+            spawn_menu_button(parent, "Save Game", MenuButton::SaveGame);
+            spawn_menu_button(parent, "Restart Level", MenuButton::RestartLevel);
+            spawn_menu_button(parent, "Options", MenuButton::Options);
+            spawn_menu_button(parent, "Quit to Menu", MenuButton::MainMenu);
+        });
+}
+
+fn spawn_game_over(mut commands: Commands) {
+    commands
+        .spawn((menu_root(), GameOverRoot))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Game Over");
+            spawn_menu_button(parent, "Try Again", MenuButton::TryAgain);
+            spawn_menu_button(parent, "Main Menu", MenuButton::MainMenu);
+            spawn_menu_button(parent, "Quit", MenuButton::Quit);
+        });
+}
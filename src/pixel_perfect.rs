@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use bevy::render::{
+    camera::RenderTarget,
+    render_resource::{ Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages },
+    view::RenderLayers,
+};
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::transform::TransformSystem;
+use bevy::window::PrimaryWindow;
+use bevy_rapier2d::prelude::RigidBody;
+
+pub struct PixelPerfectPlugin;
+
+impl Plugin for PixelPerfectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PixelPerfect>()
+            .init_resource::<SnappedBodies>()
+            .add_systems(PostStartup, setup_pixel_perfect.run_if(pixel_perfect_enabled))
+            .add_systems(Update, fit_upscaled_view.run_if(pixel_perfect_enabled))
+            .add_systems(
+                PostUpdate,
+                (snap_sprites_to_pixels, snap_bodies_to_pixels)
+                    .after(TransformSystem::TransformPropagate)
+                    .run_if(pixel_perfect_enabled)
+            )
+            .add_systems(First, unsnap_bodies);
+    }
+}
+
+/// Renders the world at a fixed low resolution and scales it up by whole numbers,
+/// with black bars filling whatever is left of the window.
+#[derive(Resource)]
+pub struct PixelPerfect {
+    pub enabled: bool,
+    pub resolution: UVec2,
+}
+
+impl Default for PixelPerfect {
+    fn default() -> PixelPerfect {
+        PixelPerfect {
+            enabled: true,
+            resolution: UVec2::new(640, 360),
+        }
+    }
+}
+
+pub fn pixel_perfect_enabled(pixel_perfect: Res<PixelPerfect>) -> bool {
+    pixel_perfect.enabled
+}
+
+/// Draws the low resolution image to the window, the world camera never sees this layer.
+#[derive(Component)]
+pub struct UpscaleCamera;
+
+#[derive(Component)]
+pub struct UpscaledView;
+
+const UPSCALE_LAYER: u8 = 1;
+
+fn setup_pixel_perfect(
+    mut commands: Commands,
+    pixel_perfect: Res<PixelPerfect>,
+    mut images: ResMut<Assets<Image>>,
+    mut world_cameras: Query<(Entity, &mut Camera), With<Camera2d>>
+) {
+    let size = Extent3d {
+        width: pixel_perfect.resolution.x,
+        height: pixel_perfect.resolution.y,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("pixel_perfect_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING |
+            TextureUsages::COPY_DST |
+            TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    let target = images.add(image);
+
+    // UI stays on the window camera at full resolution
+    for (entity, mut camera) in &mut world_cameras {
+        camera.target = RenderTarget::Image(target.clone());
+        commands.entity(entity).insert(UiCameraConfig { show_ui: false });
+    }
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+            },
+            ..default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+        UpscaleCamera,
+    ));
+
+    commands.spawn((
+        SpriteBundle {
+            texture: target,
+            ..default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+        UpscaledView,
+    ));
+
+    // Nothing to blend between pixels anymore
+    commands.insert_resource(Msaa::Off);
+}
+
+/// Largest whole number scale that fits the window, at least 1 even if the window is tiny.
+fn fit_upscaled_view(
+    pixel_perfect: Res<PixelPerfect>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut view_query: Query<&mut Transform, With<UpscaledView>>
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let resolution = pixel_perfect.resolution.as_vec2();
+    let physical = Vec2::new(window.physical_width() as f32, window.physical_height() as f32);
+    let factor = (physical / resolution).floor().min_element().max(1.0);
+
+    // The upscale camera works in logical pixels
+    let scale = factor / (window.scale_factor() as f32);
+    for mut transform in &mut view_query {
+        if transform.scale.x != scale {
+            transform.scale = Vec3::new(scale, scale, 1.0);
+        }
+    }
+}
+
+/// Where rigid bodies really were before they got rounded for rendering.
+#[derive(Resource, Default)]
+struct SnappedBodies {
+    translations: Vec<(Entity, Vec3)>,
+}
+
+/// Rounds what gets rendered, after propagation so the `Transform`s keep their precision.
+/// Rigid bodies go through `snap_bodies_to_pixels` instead.
+#[allow(clippy::type_complexity)]
+fn snap_sprites_to_pixels(
+    mut sprite_query: Query<
+        &mut GlobalTransform,
+        (Or<(With<Sprite>, With<TextureAtlasSprite>)>, Without<RigidBody>, Without<UpscaledView>)
+    >
+) {
+    for mut global_transform in &mut sprite_query {
+        let mut affine = global_transform.affine();
+        let rounded = affine.translation.round();
+        if rounded != affine.translation {
+            affine.translation = rounded;
+            *global_transform = GlobalTransform::from(affine);
+        }
+    }
+}
+
+/// Rounds rigid bodies for this frame's render only. Without change detection and put back
+/// by `unsnap_bodies`, so neither rapier nor gameplay ever sees the rounded position.
+#[allow(clippy::type_complexity)]
+fn snap_bodies_to_pixels(
+    mut body_query: Query<
+        (Entity, &mut GlobalTransform),
+        (Or<(With<Sprite>, With<TextureAtlasSprite>)>, With<RigidBody>)
+    >,
+    mut snapped_bodies: ResMut<SnappedBodies>
+) {
+    for (entity, mut global_transform) in &mut body_query {
+        let mut affine = global_transform.affine();
+        let rounded = affine.translation.round();
+        if rounded != affine.translation {
+            snapped_bodies.translations.push((entity, affine.translation.into()));
+            affine.translation = rounded;
+            *global_transform.bypass_change_detection() = GlobalTransform::from(affine);
+        }
+    }
+}
+
+fn unsnap_bodies(mut body_query: Query<&mut GlobalTransform>, mut snapped_bodies: ResMut<SnappedBodies>) {
+    for (entity, translation) in snapped_bodies.translations.drain(..) {
+        let Ok(mut global_transform) = body_query.get_mut(entity) else {
+            continue;
+        };
+
+        let mut affine = global_transform.affine();
+        affine.translation = translation.into();
+        *global_transform.bypass_change_detection() = GlobalTransform::from(affine);
+    }
+}
@@ -1,20 +1,23 @@
 use crate::components::*;
-use bevy::{ prelude::*, render::render_resource::Texture };
-use bevy_ecs_ldtk::prelude::*;
-use bevy_inspector_egui::egui::Key;
-use bevy::{ prelude::*, utils::HashMap };
+use bevy::prelude::*;
 // window::PrimaryWindow };
-use bevy_rapier2d::prelude::{ Collider, RigidBody };
 use bevy_rapier2d::dynamics::GravityScale;
-use bevy_rapier2d::control::CharacterAutostep;
 
 use bevy_rapier2d::prelude::*;
 
+pub const RUN_SPEED: f32 = 200.0;
+pub const JUMP_SPEED: f32 = 500.0;
+pub const AIR_DASHES: u32 = 1;
+
+#[allow(clippy::type_complexity)]
 pub fn player_movement(
     input: Res<Input<KeyCode>>,
-    mut query: Query<(&mut Velocity, &GroundDetection, &mut TextureAtlasSprite, &mut GravityScale), With<Player>>
+    mut query: Query<
+        (&mut Velocity, &GroundDetection, &mut TextureAtlasSprite, &mut GravityScale, &PlayerStats, &mut AirDash),
+        With<Player>
+    >
 ) {
-    for (mut velocity, ground_detection,sprite, mut gravity) in &mut query {
+    for (mut velocity, ground_detection, _sprite, mut gravity, stats, mut air_dash) in &mut query {
         let right = if input.pressed(KeyCode::D) { 1.0 } else { 0.0 };
 
         let left = if input.pressed(KeyCode::A) { 1.0 } else { 0.0 };
@@ -31,25 +34,45 @@ pub fn player_movement(
 
         ////////////////////////////////
 
-        if input.pressed(KeyCode::L) && (!ground_detection.on_ground) {
+        // Each jump gets `stats.air_dashes` dashes, held for as long as L is
+        if ground_detection.on_ground {
+            air_dash.used = 0;
+            air_dash.active = false;
+        } else if input.just_pressed(KeyCode::L) && air_dash.used < stats.air_dashes {
+            air_dash.used += 1;
+            air_dash.active = true;
+        }
+        air_dash.active &= input.pressed(KeyCode::L);
+
+        if air_dash.active {
             *gravity = GravityScale(0.0);
             let dodge_direction = if velocity.linvel.x >= 0.0 { 1.0 } else { -1.0 };
             velocity.linvel.x += 200.0 * dodge_direction;
             velocity.linvel.y = 0.0;
+        } else if ground_detection.on_ground && ground_detection.slope.is_some() {
+            *gravity = GravityScale(0.0);
         } else {
             *gravity = GravityScale(1.0);
         }
 //////////////////////////////////////////////
-        velocity.linvel.x = (right - left) * 200.0 * speed_multiplier;
+        velocity.linvel.x = (right - left) * stats.run_speed * speed_multiplier;
 
         if input.pressed(KeyCode::L){
             let dodge_direction = if velocity.linvel.x >= 0.0 { 1.0 } else { -1.0 };
             velocity.linvel.x += 200.0 * dodge_direction;
         }
 
+        // Walk along the slope surface so going downhill doesn't launch the player off it.
+        // The sensor still touches the slope for a few frames after a jump, so leave those alone.
+        if let (true, Some(slope)) = (ground_detection.on_ground, ground_detection.slope) {
+            if velocity.linvel.y < stats.jump_speed * 0.9 {
+                let normal = slope.normal();
+                velocity.linvel.y = (-velocity.linvel.x * normal.x) / normal.y;
+            }
+        }
 
         if input.just_pressed(KeyCode::W) && ground_detection.on_ground {
-            velocity.linvel.y = 500.0;
+            velocity.linvel.y = stats.jump_speed;
         }
 
 
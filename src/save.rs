@@ -0,0 +1,411 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{ Deserialize, Serialize };
+use std::fs;
+use std::path::PathBuf;
+
+use crate::checkpoints::{ CheckpointLocation, RespawnPoint };
+use crate::components::{ Health, Player };
+use crate::inventory::Inventory;
+use crate::menu::{ menu_root, spawn_menu_button, spawn_menu_title };
+use crate::state::GameState;
+use crate::systems::{ CameraLevel, RestartWorld };
+use crate::world_state::WorldState;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlots>()
+            .init_resource::<LoadMenu>()
+            .init_resource::<PendingLoad>()
+            .init_resource::<LevelStart>()
+            .add_event::<SaveGame>()
+            .add_event::<LoadGame>()
+            .add_event::<RestartLevel>()
+            .add_systems(Update, autosave_at_checkpoints.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, write_save)
+            .add_systems(Update, read_save)
+            .add_systems(Update, (apply_pending_load, release_waiting_player).chain())
+            .add_systems(Update, remember_level_start.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, restart_level)
+            .add_systems(Update, reset_level_start)
+            .add_systems(Update, spawn_load_menu)
+            .add_systems(Update, load_menu_buttons);
+    }
+}
+
+const SAVE_DIRECTORY: &str = "saves";
+pub const SAVE_SLOTS: usize = 3;
+
+/// Bumped whenever the format changes in a way `#[serde(default)]` can't paper over.
+const SAVE_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub level_iid: String,
+    /// Only for showing in the load menu.
+    pub level_identifier: String,
+    pub position: Vec2,
+    pub health: i32,
+    pub max_health: i32,
+    #[serde(default)]
+    pub checkpoint: Option<CheckpointLocation>,
+    #[serde(default)]
+    pub world: WorldState,
+    #[serde(default)]
+    pub inventory: Inventory,
+}
+
+fn slot_path(slot: usize) -> PathBuf {
+    PathBuf::from(SAVE_DIRECTORY).join(format!("slot_{}.ron", slot + 1))
+}
+
+/// `Ok(None)` for an empty slot, `Err` with a readable reason if the file is there but unusable.
+pub fn read_slot(slot: usize) -> Result<Option<SaveData>, String> {
+    let path = slot_path(slot);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let save: SaveData = ron::from_str(&contents).map_err(|error| format!("{}: {}", path.display(), error))?;
+    if save.version > SAVE_VERSION {
+        return Err(
+            format!("{}: saved by a newer version ({} > {})", path.display(), save.version, SAVE_VERSION)
+        );
+    }
+
+    Ok(Some(save))
+}
+
+fn write_slot(slot: usize, save: &SaveData) -> Result<(), String> {
+    let path = slot_path(slot);
+    fs::create_dir_all(SAVE_DIRECTORY).map_err(|error| format!("{}: {}", SAVE_DIRECTORY, error))?;
+    let contents = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())
+        .map_err(|error| error.to_string())?;
+    fs::write(&path, contents).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// Slot new saves go to, picked when starting a new game or loading one.
+#[derive(Resource, Default)]
+pub struct SaveSlots {
+    pub active: usize,
+}
+
+impl SaveSlots {
+    /// First slot without a save, `None` if they're all taken.
+    pub fn first_free() -> Option<usize> {
+        (0..SAVE_SLOTS).find(|slot| !slot_path(*slot).exists())
+    }
+}
+
+#[derive(Event)]
+pub struct SaveGame;
+
+#[derive(Event)]
+pub struct LoadGame {
+    pub slot: usize,
+}
+
+/// Save waiting for the restarted world to spawn its player.
+#[derive(Resource, Default)]
+pub struct PendingLoad {
+    pub save: Option<SaveData>,
+}
+
+/// Goes back to how things were when the player walked into the level they're in.
+#[derive(Event)]
+pub struct RestartLevel;
+
+/// Snapshot taken on entering a level, loaded like a save by `RestartLevel`.
+#[derive(Resource, Default)]
+pub struct LevelStart {
+    pub save: Option<SaveData>,
+}
+
+fn autosave_at_checkpoints(respawn_point: Res<RespawnPoint>, mut saves: EventWriter<SaveGame>) {
+    if respawn_point.is_changed() && respawn_point.checkpoint.is_some() {
+        saves.send(SaveGame);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_save(
+    mut saves: EventReader<SaveGame>,
+    player_query: Query<(&Transform, &Health, &Inventory), With<Player>>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    camera_level: Res<CameraLevel>,
+    respawn_point: Res<RespawnPoint>,
+    world_state: Res<WorldState>,
+    save_slots: Res<SaveSlots>
+) {
+    if saves.is_empty() {
+        return;
+    }
+    saves.clear();
+
+    let (Ok((transform, health, inventory)), Some(level_iid)) = (player_query.get_single(), &camera_level.iid) else {
+        warn!("Nothing to save, the player isn't in a level");
+        return;
+    };
+    let level_identifier = level_query
+        .iter()
+        .filter_map(|level_handle| ldtk_levels.get(level_handle))
+        .find(|ldtk_level| ldtk_level.level.iid == *level_iid)
+        .map(|ldtk_level| ldtk_level.level.identifier.clone())
+        .unwrap_or_default();
+
+    let save = SaveData {
+        version: SAVE_VERSION,
+        level_iid: level_iid.clone(),
+        level_identifier,
+        position: transform.translation.truncate(),
+        health: health.current,
+        max_health: health.max,
+        checkpoint: respawn_point.checkpoint.clone(),
+        world: world_state.clone(),
+        inventory: inventory.clone(),
+    };
+
+    match write_slot(save_slots.active, &save) {
+        Ok(()) => info!("Saved to slot {}", save_slots.active + 1),
+        Err(error) => error!("Could not save: {}", error),
+    }
+}
+
+/// Restarts the world and keeps the save around until the new player shows up.
+fn read_save(
+    mut loads: EventReader<LoadGame>,
+    mut pending_load: ResMut<PendingLoad>,
+    mut save_slots: ResMut<SaveSlots>,
+    mut restarts: EventWriter<RestartWorld>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    for load in loads.iter() {
+        match read_slot(load.slot) {
+            Ok(Some(save)) => {
+                pending_load.save = Some(save);
+                save_slots.active = load.slot;
+                restarts.send(RestartWorld);
+                next_state.set(GameState::Playing);
+            }
+            Ok(None) => warn!("Save slot {} is empty", load.slot + 1),
+            Err(error) => error!("Could not load: {}", error),
+        }
+    }
+}
+
+/// On a player that's floating in place until the level they were moved into has
+/// spawned its colliders, so they don't drop through the floor.
+#[derive(Component)]
+pub struct WaitingForLevel {
+    pub level_iid: String,
+}
+
+/// Runs once the restarted world has spawned the player, after `RestartWorld` reset everything.
+#[allow(clippy::type_complexity)]
+fn apply_pending_load(
+    mut commands: Commands,
+    mut pending_load: ResMut<PendingLoad>,
+    mut player_query: Query<
+        (Entity, &mut Transform, &mut Health, &mut Inventory, &mut GravityScale, &mut Velocity),
+        Added<Player>
+    >,
+    mut level_selection: ResMut<LevelSelection>,
+    mut respawn_point: ResMut<RespawnPoint>
+) {
+    if pending_load.save.is_none() {
+        return;
+    }
+    let Ok((entity, mut transform, mut health, mut inventory, mut gravity, mut velocity)) = player_query.get_single_mut() else {
+        return;
+    };
+    let Some(save) = pending_load.save.take() else {
+        return;
+    };
+
+    transform.translation.x = save.position.x;
+    transform.translation.y = save.position.y;
+    *gravity = GravityScale(0.0);
+    *velocity = Velocity::zero();
+    health.current = save.health;
+    health.max = save.max_health;
+    *inventory = save.inventory;
+    respawn_point.checkpoint = save.checkpoint;
+    commands.entity(entity).insert(WaitingForLevel { level_iid: save.level_iid.clone() });
+    *level_selection = LevelSelection::Iid(save.level_iid);
+}
+
+/// Lets the player drop once their level is transformed, its colliders are spawned the frame
+/// its cells are.
+fn release_waiting_player(
+    mut commands: Commands,
+    mut level_events: EventReader<LevelEvent>,
+    mut player_query: Query<(Entity, &WaitingForLevel, &mut GravityScale, &mut Velocity)>
+) {
+    let transformed: Vec<&String> = level_events
+        .iter()
+        .filter_map(|level_event| match level_event {
+            LevelEvent::Transformed(iid) => Some(iid),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, waiting, mut gravity, mut velocity) in &mut player_query {
+        if !transformed.contains(&&waiting.level_iid) {
+            *velocity = Velocity::zero();
+            continue;
+        }
+
+        *gravity = GravityScale(1.0);
+        commands.entity(entity).remove::<WaitingForLevel>();
+    }
+}
+
+fn remember_level_start(
+    player_query: Query<(&Transform, &Health, &Inventory), With<Player>>,
+    camera_level: Res<CameraLevel>,
+    respawn_point: Res<RespawnPoint>,
+    world_state: Res<WorldState>,
+    mut level_start: ResMut<LevelStart>
+) {
+    let Some(level_iid) = &camera_level.iid else {
+        return;
+    };
+    if level_start.save.as_ref().is_some_and(|save| save.level_iid == *level_iid) {
+        return;
+    }
+    let Ok((transform, health, inventory)) = player_query.get_single() else {
+        return;
+    };
+
+    level_start.save = Some(SaveData {
+        version: SAVE_VERSION,
+        level_iid: level_iid.clone(),
+        level_identifier: String::new(),
+        position: transform.translation.truncate(),
+        health: health.current,
+        max_health: health.max,
+        checkpoint: respawn_point.checkpoint.clone(),
+        world: world_state.clone(),
+        inventory: inventory.clone(),
+    });
+}
+
+/// Restarts the world with the level start waiting to be applied, the same way a save loads.
+fn restart_level(
+    mut level_restarts: EventReader<RestartLevel>,
+    level_start: Res<LevelStart>,
+    mut pending_load: ResMut<PendingLoad>,
+    mut restarts: EventWriter<RestartWorld>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    if level_restarts.is_empty() {
+        return;
+    }
+    level_restarts.clear();
+
+    if level_start.save.is_none() {
+        warn!("No level to restart, starting over");
+    }
+    pending_load.save = level_start.save.clone();
+    restarts.send(RestartWorld);
+    next_state.set(GameState::Playing);
+}
+
+/// Taken again once the restarted world frames a level.
+fn reset_level_start(mut restarts: EventReader<RestartWorld>, mut level_start: ResMut<LevelStart>) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    level_start.save = None;
+}
+
+/// Slot list for loading a save, or for picking which one a new game overwrites when
+/// every slot is taken.
+#[derive(Resource, Default)]
+pub struct LoadMenu {
+    pub open: bool,
+    pub overwrite: bool,
+}
+
+#[derive(Component)]
+pub struct LoadMenuRoot;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub enum LoadMenuButton {
+    Slot(usize),
+    Back,
+}
+
+fn spawn_load_menu(
+    mut commands: Commands,
+    load_menu: Res<LoadMenu>,
+    root_query: Query<Entity, With<LoadMenuRoot>>
+) {
+    if !load_menu.is_changed() {
+        return;
+    }
+
+    for entity in &root_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !load_menu.open {
+        return;
+    }
+
+    let root = NodeBundle {
+        z_index: ZIndex::Global(60),
+        ..menu_root()
+    };
+    commands
+        .spawn((root, LoadMenuRoot))
+        .with_children(|parent| {
+            spawn_menu_title(parent, if load_menu.overwrite { "Overwrite Which Save?" } else { "Load Game" });
+            for slot in 0..SAVE_SLOTS {
+                let label = match read_slot(slot) {
+                    Ok(Some(save)) => format!("Slot {}: {}", slot + 1, save.level_identifier),
+                    Ok(None) => format!("Slot {}: Empty", slot + 1),
+                    Err(error) => {
+                        warn!("{}", error);
+                        format!("Slot {}: Unreadable", slot + 1)
+                    }
+                };
+                spawn_menu_button(parent, label, LoadMenuButton::Slot(slot));
+            }
+            spawn_menu_button(parent, "Back", LoadMenuButton::Back);
+        });
+}
+
+fn load_menu_buttons(
+    button_query: Query<(&Interaction, &LoadMenuButton), Changed<Interaction>>,
+    mut load_menu: ResMut<LoadMenu>,
+    mut save_slots: ResMut<SaveSlots>,
+    mut loads: EventWriter<LoadGame>,
+    mut restarts: EventWriter<RestartWorld>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            // The old save only goes away once the new game saves over it
+            LoadMenuButton::Slot(slot) if load_menu.overwrite => {
+                save_slots.active = *slot;
+                restarts.send(RestartWorld);
+                next_state.set(GameState::Playing);
+            }
+            LoadMenuButton::Slot(slot) => loads.send(LoadGame { slot: *slot }),
+            LoadMenuButton::Back => {}
+        }
+        load_menu.open = false;
+    }
+}
@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use bevy::window::{ PresentMode, PrimaryWindow, WindowMode, WindowResolution };
+use serde::{ Deserialize, Serialize };
+use std::fs;
+
+use crate::menu::{ menu_root, spawn_menu_button, spawn_menu_title };
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OptionsMenu>()
+            .add_systems(Startup, warn_unreadable_settings)
+            .add_systems(Update, apply_display_settings)
+            .add_systems(Update, save_display_settings)
+            .add_systems(Update, spawn_options_menu)
+            .add_systems(Update, options_menu_buttons)
+            .add_systems(Update, update_options_labels);
+    }
+}
+
+const SETTINGS_PATH: &str = "settings.ron";
+
+const RESOLUTIONS: [(u32, u32); 4] = [(640, 360), (1280, 720), (1920, 1080), (2560, 1440)];
+const UI_SCALES: [f64; 5] = [0.75, 1.0, 1.25, 1.5, 2.0];
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    Fullscreen,
+    Borderless,
+}
+
+impl DisplayMode {
+    fn next(self) -> DisplayMode {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Windowed,
+        }
+    }
+
+    fn window_mode(self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+        }
+    }
+}
+
+/// Window and display options, read from `settings.ron` on startup and written back
+/// whenever they change.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub resolution: (u32, u32),
+    pub mode: DisplayMode,
+    pub vsync: bool,
+    pub ui_scale: f64,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> DisplaySettings {
+        DisplaySettings {
+            resolution: (1280, 720),
+            mode: DisplayMode::Windowed,
+            vsync: true,
+            ui_scale: 1.0,
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// Falls back to the defaults if the file is missing or can't be parsed.
+    /// Runs before the app exists, a parse error is reported once the logger is up.
+    pub fn load() -> DisplaySettings {
+        DisplaySettings::read().and_then(Result::ok).unwrap_or_default()
+    }
+
+    fn read() -> Option<Result<DisplaySettings, ron::error::SpannedError>> {
+        let contents = fs::read_to_string(SETTINGS_PATH).ok()?;
+        Some(ron::from_str(&contents))
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| fs::write(SETTINGS_PATH, contents).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            warn!("Could not save {}: {}", SETTINGS_PATH, error);
+        }
+    }
+
+    /// Primary window to hand to the `WindowPlugin`, so the first frame already has the right size.
+    pub fn window(&self) -> Window {
+        Window {
+            title: "Dungeon-Madness".into(),
+            resolution: WindowResolution::new(self.resolution.0 as f32, self.resolution.1 as f32),
+            mode: self.mode.window_mode(),
+            present_mode: self.present_mode(),
+            ..default()
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync }
+    }
+}
+
+fn warn_unreadable_settings() {
+    if let Some(Err(error)) = DisplaySettings::read() {
+        warn!("Could not parse {}, using default settings: {}", SETTINGS_PATH, error);
+    }
+}
+
+fn apply_display_settings(
+    settings: Res<DisplaySettings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut window in &mut window_query {
+        window.resolution.set(settings.resolution.0 as f32, settings.resolution.1 as f32);
+        window.mode = settings.mode.window_mode();
+        window.present_mode = settings.present_mode();
+    }
+    ui_scale.scale = settings.ui_scale;
+}
+
+fn save_display_settings(settings: Res<DisplaySettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct OptionsMenu {
+    pub open: bool,
+}
+
+#[derive(Component)]
+pub struct OptionsMenuRoot;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub enum OptionsButton {
+    Resolution,
+    Mode,
+    Vsync,
+    UiScale,
+    Back,
+}
+
+impl OptionsButton {
+    fn label(self, settings: &DisplaySettings) -> String {
+        match self {
+            OptionsButton::Resolution =>
+                format!("Resolution: {}x{}", settings.resolution.0, settings.resolution.1),
+            OptionsButton::Mode => format!("Mode: {:?}", settings.mode),
+            OptionsButton::Vsync => format!("VSync: {}", if settings.vsync { "On" } else { "Off" }),
+            OptionsButton::UiScale => format!("UI Scale: {}", settings.ui_scale),
+            OptionsButton::Back => "Back".to_string(),
+        }
+    }
+}
+
+/// The entry after `current`, or the first one if `current` isn't in the list.
+fn cycle<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let index = options
+        .iter()
+        .position(|option| *option == current)
+        .map_or(0, |index| (index + 1) % options.len());
+    options[index]
+}
+
+fn spawn_options_menu(
+    mut commands: Commands,
+    options_menu: Res<OptionsMenu>,
+    settings: Res<DisplaySettings>,
+    root_query: Query<Entity, With<OptionsMenuRoot>>
+) {
+    if !options_menu.is_changed() {
+        return;
+    }
+
+    for entity in &root_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !options_menu.open {
+        return;
+    }
+
+    // Drawn over whichever menu opened it
+    let root = NodeBundle {
+        z_index: ZIndex::Global(60),
+        ..menu_root()
+    };
+    commands
+        .spawn((root, OptionsMenuRoot))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Options");
+            for button in [
+                OptionsButton::Resolution,
+                OptionsButton::Mode,
+                OptionsButton::Vsync,
+                OptionsButton::UiScale,
+                OptionsButton::Back,
+            ] {
+                spawn_menu_button(parent, button.label(&settings), button);
+            }
+        });
+}
+
+fn options_menu_buttons(
+    button_query: Query<(&Interaction, &OptionsButton), Changed<Interaction>>,
+    mut settings: ResMut<DisplaySettings>,
+    mut options_menu: ResMut<OptionsMenu>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            OptionsButton::Resolution => {
+                settings.resolution = cycle(&RESOLUTIONS, settings.resolution);
+            }
+            OptionsButton::Mode => {
+                settings.mode = settings.mode.next();
+            }
+            OptionsButton::Vsync => {
+                settings.vsync = !settings.vsync;
+            }
+            OptionsButton::UiScale => {
+                settings.ui_scale = cycle(&UI_SCALES, settings.ui_scale);
+            }
+            OptionsButton::Back => {
+                options_menu.open = false;
+            }
+        }
+    }
+}
+
+fn update_options_labels(
+    settings: Res<DisplaySettings>,
+    button_query: Query<(&OptionsButton, &Children)>,
+    mut text_query: Query<&mut Text>
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (button, children) in &button_query {
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = button.label(&settings);
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::collections::HashSet;
+
+use crate::components::{ Lever, Player, PressurePlate, TriggerVolume, LEVER_COLOR, LEVER_PULLED_COLOR };
+use crate::interaction::InteractEvent;
+use crate::state::GameState;
+use crate::world_state::{ EntityState, Opened, WorldState };
+
+pub struct SignalPlugin;
+
+impl Plugin for SignalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Signal>()
+            .add_systems(Update, setup_signal_sensors)
+            .add_systems(Update, restore_activated_senders)
+            .add_systems(Update, pull_levers.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, track_pressure_plates.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, track_trigger_volumes.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, show_lever_state)
+            .add_systems(Update, show_pressure_plate_state)
+            .add_systems(Update, (send_signals, receive_signals).chain());
+    }
+}
+
+/// Turns on and off, telling the LDtk entities in `targets` (an `Array<EntityRef>` field)
+/// whenever it does.
+#[derive(Clone, Debug, Default, Component)]
+pub struct SignalSender {
+    /// Iids of the entities to signal.
+    pub targets: Vec<String>,
+    pub active: bool,
+}
+
+impl From<&EntityInstance> for SignalSender {
+    fn from(entity_instance: &EntityInstance) -> SignalSender {
+        SignalSender {
+            targets: entity_instance
+                .get_maybe_entity_refs_field("targets")
+                .map(|references| {
+                    references
+                        .iter()
+                        .flatten()
+                        .map(|reference| reference.entity_iid.clone())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            active: false,
+        }
+    }
+}
+
+/// Only touches the sender when it actually flips, so `Changed` means a new signal.
+fn set_active(sender: &mut Mut<SignalSender>, active: bool) {
+    if sender.active != active {
+        sender.active = active;
+    }
+}
+
+/// Something doors, platforms and spawners react to. On while any of the senders targeting
+/// it are, changes only when it flips so receivers can go by `Changed<SignalReceiver>`.
+#[derive(Clone, Debug, Default, Component)]
+pub struct SignalReceiver {
+    pub active: bool,
+    sources: HashSet<Entity>,
+}
+
+/// A sender turning on or off, one per target.
+#[derive(Event, Clone, Debug)]
+pub struct Signal {
+    pub source: Entity,
+    pub target: Entity,
+    pub active: bool,
+}
+
+/// Plates and trigger volumes watch for bodies with a sensor covering the whole entity.
+#[allow(clippy::type_complexity)]
+fn setup_signal_sensors(
+    mut commands: Commands,
+    sensor_query: Query<
+        (Entity, Option<&PressurePlate>, Option<&TriggerVolume>),
+        Or<(Added<PressurePlate>, Added<TriggerVolume>)>
+    >
+) {
+    for (entity, plate, trigger) in &sensor_query {
+        let Some(half_size) = plate.map(|plate| plate.half_size).or(trigger.map(|trigger| trigger.half_size)) else {
+            continue;
+        };
+
+        commands
+            .entity(entity)
+            .insert((ActiveEvents::COLLISION_EVENTS, Collider::cuboid(half_size.x, half_size.y), Sensor));
+    }
+}
+
+/// Levers and one-shot triggers come back the way they were left.
+fn restore_activated_senders(mut sender_query: Query<&mut SignalSender, Added<Opened>>) {
+    for mut sender in &mut sender_query {
+        set_active(&mut sender, true);
+    }
+}
+
+fn pull_levers(
+    mut interactions: EventReader<InteractEvent>,
+    mut lever_query: Query<(&mut SignalSender, &EntityIid), With<Lever>>,
+    mut world_state: ResMut<WorldState>
+) {
+    for interaction in interactions.iter() {
+        let Ok((mut sender, iid)) = lever_query.get_mut(interaction.target) else {
+            continue;
+        };
+
+        sender.active = !sender.active;
+        if sender.active {
+            world_state.set(iid.as_str(), EntityState::Opened);
+        } else {
+            world_state.clear(iid.as_str());
+        }
+    }
+}
+
+/// Pressed by anything with a dynamic body, so dropped items can hold a plate down too.
+fn track_pressure_plates(
+    mut collisions: EventReader<CollisionEvent>,
+    mut plate_query: Query<(&mut PressurePlate, &mut SignalSender)>,
+    body_query: Query<&RigidBody, Without<Sensor>>
+) {
+    for collision_event in collisions.iter() {
+        let (e1, e2, inside) = match collision_event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
+        };
+
+        for (plate_entity, other) in [(e1, e2), (e2, e1)] {
+            if !matches!(body_query.get(other), Ok(RigidBody::Dynamic)) {
+                continue;
+            }
+            let Ok((mut plate, mut sender)) = plate_query.get_mut(plate_entity) else {
+                continue;
+            };
+
+            if inside {
+                plate.pressing.insert(other);
+            } else {
+                plate.pressing.remove(&other);
+            }
+            set_active(&mut sender, !plate.pressing.is_empty());
+        }
+    }
+}
+
+fn track_trigger_volumes(
+    mut collisions: EventReader<CollisionEvent>,
+    mut trigger_query: Query<(&TriggerVolume, &mut SignalSender, &EntityIid)>,
+    player_query: Query<(), With<Player>>,
+    mut world_state: ResMut<WorldState>
+) {
+    for collision_event in collisions.iter() {
+        let (e1, e2, inside) = match collision_event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
+        };
+
+        for (trigger_entity, other) in [(e1, e2), (e2, e1)] {
+            if !player_query.contains(other) {
+                continue;
+            }
+            let Ok((trigger, mut sender, iid)) = trigger_query.get_mut(trigger_entity) else {
+                continue;
+            };
+
+            if trigger.once {
+                if inside && !sender.active {
+                    sender.active = true;
+                    world_state.set(iid.as_str(), EntityState::Opened);
+                }
+            } else {
+                set_active(&mut sender, inside);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn show_lever_state(mut lever_query: Query<(&SignalSender, &mut Sprite), (With<Lever>, Changed<SignalSender>)>) {
+    for (sender, mut sprite) in &mut lever_query {
+        sprite.color = if sender.active { LEVER_PULLED_COLOR } else { LEVER_COLOR };
+    }
+}
+
+/// Sinks into the floor while pressed.
+fn show_pressure_plate_state(
+    mut plate_query: Query<(&PressurePlate, &SignalSender, &mut Sprite), Changed<SignalSender>>
+) {
+    for (plate, sender, mut sprite) in &mut plate_query {
+        let size = plate.half_size * 2.0;
+        let height = if sender.active { size.y / 2.0 } else { size.y };
+        sprite.custom_size = Some(Vec2::new(size.x, height));
+        sprite.anchor = Anchor::Custom(Vec2::new(0.0, size.y / 2.0 / height - 0.5));
+    }
+}
+
+fn send_signals(
+    sender_query: Query<(Entity, &SignalSender), Changed<SignalSender>>,
+    target_query: Query<(Entity, &EntityIid)>,
+    mut signals: EventWriter<Signal>
+) {
+    for (source, sender) in &sender_query {
+        for (target, iid) in &target_query {
+            if sender.targets.iter().any(|target_iid| target_iid == iid.as_str()) {
+                signals.send(Signal {
+                    source,
+                    target,
+                    active: sender.active,
+                });
+            }
+        }
+    }
+}
+
+fn receive_signals(mut signals: EventReader<Signal>, mut receiver_query: Query<&mut SignalReceiver>) {
+    for signal in signals.iter() {
+        let Ok(mut receiver) = receiver_query.get_mut(signal.target) else {
+            warn!("Signal sent to {:?}, which can't receive one", signal.target);
+            continue;
+        };
+
+        let was_active = receiver.active;
+        let state = receiver.bypass_change_detection();
+        if signal.active {
+            state.sources.insert(signal.source);
+        } else {
+            state.sources.remove(&signal.source);
+        }
+        state.active = !state.sources.is_empty();
+
+        if state.active != was_active {
+            receiver.set_changed();
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::save::LoadMenu;
+use crate::settings::OptionsMenu;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .add_systems(Update, toggle_pause)
+            .add_systems(Update, pause_physics.run_if(state_changed::<GameState>()));
+    }
+}
+
+#[derive(States, Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum GameState {
+    #[default]
+    Loading,
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Escape or Start closes the options or load menu if one is open, otherwise pauses or resumes.
+fn toggle_pause(
+    input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut options_menu: ResMut<OptionsMenu>,
+    mut load_menu: ResMut<LoadMenu>
+) {
+    let start_pressed = gamepads
+        .iter()
+        .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start)));
+    if !input.just_pressed(KeyCode::Escape) && !start_pressed {
+        return;
+    }
+    if options_menu.open || load_menu.open {
+        options_menu.open = false;
+        load_menu.open = false;
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+/// The world only simulates while playing, menus shown over it leave it frozen.
+fn pause_physics(state: Res<State<GameState>>, mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = *state.get() == GameState::Playing;
+}
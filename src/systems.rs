@@ -1,15 +1,19 @@
 use crate::components::*;
-use bevy::{ prelude::*, render::render_resource::Texture };
+use crate::feedback::ScreenShake;
+use crate::pixel_perfect::{ PixelPerfect, UpscaleCamera };
+use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
-use bevy_inspector_egui::egui::Key;
+use bevy_rapier2d::prelude::Velocity;
 
-use std::{ collections::{ HashMap, HashSet }, f32::consts::E };
-
-use bevy_rapier2d::prelude::*;
+pub const START_LEVEL_UID: i32 = 0;
 
 pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2dBundle::default());
 
+    spawn_world(&mut commands, &asset_server);
+}
+
+fn spawn_world(commands: &mut Commands, asset_server: &AssetServer) {
     commands.spawn(LdtkWorldBundle {
         ldtk_handle: asset_server.load("Dungeon.ldtk"),
         ..Default::default()
@@ -22,66 +26,247 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     // });
 }
 
+/// Throws away the current run and spawns the world again from the start level.
+#[derive(Event)]
+pub struct RestartWorld;
+
+pub fn restart_world(
+    mut commands: Commands,
+    mut restarts: EventReader<RestartWorld>,
+    world_query: Query<Entity, With<Handle<LdtkAsset>>>,
+    asset_server: Res<AssetServer>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut camera_level: ResMut<CameraLevel>
+) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    for world_entity in &world_query {
+        commands.entity(world_entity).despawn_recursive();
+    }
+    spawn_world(&mut commands, &asset_server);
+
+    *level_selection = LevelSelection::Uid(START_LEVEL_UID);
+    *camera_level = CameraLevel::default();
+}
+
 const ASPECT_RATIO: f32 = 16.0 / 9.0;
 
+const LEVEL_BLEND_SECONDS: f32 = 0.4;
+
+/// Level the camera is currently framing. When the player walks into another level the
+/// framing is blended over from where the camera was, instead of jumping to the new bounds.
+#[derive(Resource, Default)]
+pub struct CameraLevel {
+    pub iid: Option<String>,
+    blend: Timer,
+    from_translation: Vec2,
+    from_size: Vec2,
+}
+
+/// How the camera chases the player inside the level bounds.
+#[derive(Resource)]
+pub struct CameraController {
+    /// Half size of the box the player can move in without dragging the camera along.
+    pub deadzone: Vec2,
+    /// Rate the camera closes in on its target, higher is snappier.
+    pub smoothing: f32,
+    /// How far ahead of the player to look in the direction they're facing.
+    pub look_ahead: f32,
+    /// How far below the player to look while falling faster than `fall_speed`.
+    pub fall_bias: f32,
+    pub fall_speed: f32,
+    anchor: Vec2,
+    focus: Vec2,
+}
+
+impl Default for CameraController {
+    fn default() -> CameraController {
+        CameraController {
+            deadzone: Vec2::new(24.0, 32.0),
+            smoothing: 6.0,
+            look_ahead: 48.0,
+            fall_bias: 64.0,
+            fall_speed: 300.0,
+            anchor: Vec2::ZERO,
+            focus: Vec2::ZERO,
+        }
+    }
+}
+
+impl CameraController {
+    fn snap_to(&mut self, position: Vec2) {
+        self.anchor = position;
+        self.focus = position;
+    }
+
+    /// Drags the anchor along with the player once they leave the deadzone, then eases
+    /// the focus towards the anchor shifted by the look-ahead and fall bias.
+    fn follow(&mut self, position: Vec2, facing: f32, fall_speed: f32, delta_seconds: f32) {
+        let outside = (position - self.anchor).abs() - self.deadzone;
+        if outside.x > 0.0 {
+            self.anchor.x += outside.x * (position.x - self.anchor.x).signum();
+        }
+        if outside.y > 0.0 {
+            self.anchor.y += outside.y * (position.y - self.anchor.y).signum();
+        }
+
+        let mut goal = self.anchor + Vec2::new(self.look_ahead * facing, 0.0);
+        if fall_speed > self.fall_speed {
+            goal.y -= self.fall_bias;
+        }
+
+        // Frame rate independent exponential smoothing
+        let t = 1.0 - (-self.smoothing * delta_seconds).exp();
+        self.focus = self.focus.lerp(goal, t);
+    }
+}
+
+/// Size of the camera view inside a level and the bottom left corner it should sit at,
+/// both relative to the level.
+fn fit_inside_level(level: &ldtk::Level, focus_offset: Vec2) -> (Vec2, Vec2) {
+    let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+    let level_ratio = level_size.x / level_size.y;
+
+    if level_ratio > ASPECT_RATIO {
+        let height = (level_size.y / 9.0).round() * 9.0;
+        let width = height * ASPECT_RATIO;
+        let x = (focus_offset.x - width / 2.0).clamp(0.0, level_size.x - width);
+        (Vec2::new(width, height), Vec2::new(x, 0.0))
+    } else {
+        let width = (level_size.x / 16.0).round() * 16.0;
+        let height = width / ASPECT_RATIO;
+        let y = (focus_offset.y - height / 2.0).clamp(0.0, level_size.y - height);
+        (Vec2::new(width, height), Vec2::new(0.0, y))
+    }
+}
+
+/// Like `fit_inside_level`, but the view keeps the pixel perfect resolution so world pixels
+/// map one to one onto the render target. Levels smaller than the view get centered.
+fn fit_pixel_perfect(level: &ldtk::Level, view: Vec2, focus_offset: Vec2) -> Vec2 {
+    let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+    let fit_axis = |level_length: f32, view_length: f32, focus: f32| {
+        if level_length < view_length {
+            (level_length - view_length) / 2.0
+        } else {
+            (focus - view_length / 2.0).clamp(0.0, level_length - view_length)
+        }
+    };
+
+    Vec2::new(
+        fit_axis(level_size.x, view.x, focus_offset.x),
+        fit_axis(level_size.y, view.y, focus_offset.y)
+    )
+}
+
+/// Frames the level the player is in, following them with the `CameraController`, and
+/// selects it so its neighbours get spawned around it.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn camera_fit_inside_current_level(
     mut camera_query: Query<
         (&mut bevy::render::camera::OrthographicProjection, &mut Transform),
-        Without<Player>
+        (Without<Player>, Without<UpscaleCamera>)
     >,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(&Transform, &Velocity, &TextureAtlasSprite), With<Player>>,
     level_query: Query<
         (&Transform, &Handle<LdtkLevel>),
         (Without<OrthographicProjection>, Without<Player>)
     >,
-    level_selection: Res<LevelSelection>,
-    ldtk_levels: Res<Assets<LdtkLevel>>
+    mut camera_level: ResMut<CameraLevel>,
+    mut controller: ResMut<CameraController>,
+    shake: Res<ScreenShake>,
+    pixel_perfect: Res<PixelPerfect>,
+    mut level_selection: ResMut<LevelSelection>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    time: Res<Time>
 ) {
-    if let Ok(Transform { translation: player_translation, .. }) = player_query.get_single() {
-        let player_translation = *player_translation;
-
-        let (mut orthographic_projection, mut camera_transform) = camera_query.single_mut();
-
-        for (level_transform, level_handle) in &level_query {
-            if let Some(ldtk_level) = ldtk_levels.get(level_handle) {
-                let level = &ldtk_level.level;
-                if level_selection.is_match(&0, &level) {
-                    let level_ratio = (level.px_wid as f32) / (ldtk_level.level.px_hei as f32);
-                    orthographic_projection.viewport_origin = Vec2::ZERO;
-                    if level_ratio > ASPECT_RATIO {
-                        let height = ((level.px_hei as f32) / 9.0).round() * 9.0;
-                        let width = height * ASPECT_RATIO;
-                        orthographic_projection.scaling_mode =
-                            bevy::render::camera::ScalingMode::Fixed {
-                                width,
-                                height,
-                            };
-                        camera_transform.translation.x = (
-                            player_translation.x -
-                            level_transform.translation.x -
-                            width / 2.0
-                        ).clamp(0.0, (level.px_wid as f32) - width);
-                        camera_transform.translation.y = 0.0;
-                    } else {
-                        let width = ((level.px_wid as f32) / 16.0).round() * 16.0;
-                        let height = width / ASPECT_RATIO;
-                        orthographic_projection.scaling_mode =
-                            bevy::render::camera::ScalingMode::Fixed {
-                                width,
-                                height,
-                            };
-                        camera_transform.translation.y = (
-                            player_translation.y -
-                            level_transform.translation.y -
-                            height / 2.0
-                        ).clamp(0.0, (level.px_hei as f32) - height);
-                        camera_transform.translation.x = 0.0;
-                    }
-
-                    camera_transform.translation.x += level_transform.translation.x;
-                    camera_transform.translation.y += level_transform.translation.y;
-                }
-            }
+    let Ok((player_transform, player_velocity, player_sprite)) = player_query.get_single() else {
+        return;
+    };
+    let player_translation = player_transform.translation.truncate();
+
+    // Stick with the framed level while the player is still inside it, so standing on
+    // a shared edge doesn't flip back and forth between two levels
+    let mut current = None;
+    for (level_transform, level_handle) in &level_query {
+        let Some(ldtk_level) = ldtk_levels.get(level_handle) else {
+            continue;
+        };
+        let level = &ldtk_level.level;
+        let level_translation = level_transform.translation.truncate();
+        let level_bounds = Rect::from_corners(
+            level_translation,
+            level_translation + Vec2::new(level.px_wid as f32, level.px_hei as f32)
+        );
+
+        let is_framed = camera_level.iid.as_ref() == Some(&level.iid);
+        if level_bounds.contains(player_translation) && (current.is_none() || is_framed) {
+            current = Some((level_translation, level));
+        }
+    }
+    let Some((level_translation, level)) = current else {
+        return;
+    };
+
+    let (mut orthographic_projection, mut camera_transform) = camera_query.single_mut();
+    orthographic_projection.viewport_origin = Vec2::ZERO;
+
+    let entered_level = camera_level.iid.as_ref() != Some(&level.iid);
+    if entered_level {
+        controller.snap_to(player_translation);
+    } else {
+        let facing = if player_sprite.flip_x { -1.0 } else { 1.0 };
+        controller.follow(player_translation, facing, -player_velocity.linvel.y, time.delta_seconds());
+    }
+
+    let focus_offset = controller.focus - level_translation;
+    let (size, offset) = if pixel_perfect.enabled {
+        let view = pixel_perfect.resolution.as_vec2();
+        (view, fit_pixel_perfect(level, view, focus_offset))
+    } else {
+        fit_inside_level(level, focus_offset)
+    };
+    let translation = level_translation + offset;
+
+    if entered_level {
+        // Nothing to blend from when the first level shows up
+        if camera_level.iid.is_some() {
+            camera_level.from_translation = camera_transform.translation.truncate();
+            camera_level.from_size = match orthographic_projection.scaling_mode {
+                bevy::render::camera::ScalingMode::Fixed { width, height } => Vec2::new(width, height),
+                _ => size,
+            };
+            camera_level.blend = Timer::from_seconds(LEVEL_BLEND_SECONDS, TimerMode::Once);
+        }
+        camera_level.iid = Some(level.iid.clone());
+
+        let selection = LevelSelection::Iid(level.iid.clone());
+        if *level_selection != selection {
+            *level_selection = selection;
         }
     }
+
+    camera_level.blend.tick(time.delta());
+    let (size, translation) = if camera_level.blend.finished() {
+        // Shake is clamped like the framing, so it can't show anything outside the level
+        let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let min = level_translation.min(translation);
+        let max = (level_translation + level_size - size).max(translation);
+        (size, (translation + shake.offset).clamp(min, max))
+    } else {
+        // Smoothstep, eases in and out of the new framing
+        let t = camera_level.blend.percent();
+        let t = t * t * (3.0 - 2.0 * t);
+        (camera_level.from_size.lerp(size, t), camera_level.from_translation.lerp(translation, t))
+    };
+
+    orthographic_projection.scaling_mode = bevy::render::camera::ScalingMode::Fixed {
+        width: size.x,
+        height: size.y,
+    };
+    let translation = if pixel_perfect.enabled { translation.round() } else { translation };
+    camera_transform.translation.x = translation.x;
+    camera_transform.translation.y = translation.y;
 }
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{ Deserialize, Serialize };
+use std::collections::{ HashMap, HashSet };
+
+use crate::components::Breakable;
+use crate::destructible::BlockBrokenEvent;
+use crate::save::PendingLoad;
+use crate::systems::RestartWorld;
+
+pub struct WorldStatePlugin;
+
+impl Plugin for WorldStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldState>()
+            .add_systems(Update, record_broken_blocks)
+            .add_systems(Update, apply_entity_states)
+            .add_systems(Update, apply_broken_blocks)
+            .add_systems(Update, reset_world_state);
+    }
+}
+
+/// What happened to an LDtk entity that should outlive its level being despawned.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EntityState {
+    /// Defeated, picked up or otherwise gone for good.
+    Removed,
+    /// Chests, doors and switches that stay open.
+    Opened,
+}
+
+/// Added to respawned entities recorded as `EntityState::Opened`, whatever owns the entity
+/// should pick it up and skip straight to its opened look.
+#[derive(Component)]
+pub struct Opened;
+
+/// Changes to the world keyed by LDtk iids, re-applied whenever `bevy_ecs_ldtk` spawns
+/// a level again. Part of the save file.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WorldState {
+    entities: HashMap<String, EntityState>,
+    /// Broken `Breakable` cells per level iid.
+    broken_cells: HashMap<String, HashSet<(i32, i32)>>,
+}
+
+impl WorldState {
+    pub fn set(&mut self, iid: impl Into<String>, state: EntityState) {
+        self.entities.insert(iid.into(), state);
+    }
+
+    /// Forgets about the entity, for things like levers that can be switched back.
+    pub fn clear(&mut self, iid: &str) {
+        self.entities.remove(iid);
+    }
+
+    pub fn get(&self, iid: &str) -> Option<EntityState> {
+        self.entities.get(iid).copied()
+    }
+
+    fn is_broken(&self, level_iid: &str, grid_coords: GridCoords) -> bool {
+        self.broken_cells
+            .get(level_iid)
+            .is_some_and(|cells| cells.contains(&(grid_coords.x, grid_coords.y)))
+    }
+}
+
+fn apply_entity_states(
+    mut commands: Commands,
+    world_state: Res<WorldState>,
+    entity_query: Query<(Entity, &EntityIid), Added<EntityIid>>
+) {
+    for (entity, iid) in &entity_query {
+        match world_state.get(iid.as_str()) {
+            Some(EntityState::Removed) => commands.entity(entity).despawn_recursive(),
+            Some(EntityState::Opened) => {
+                commands.entity(entity).insert(Opened);
+            }
+            None => {}
+        }
+    }
+}
+
+fn record_broken_blocks(
+    mut broken_blocks: EventReader<BlockBrokenEvent>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    mut world_state: ResMut<WorldState>
+) {
+    for broken in broken_blocks.iter() {
+        let Some(ldtk_level) = level_query
+            .get(broken.level)
+            .ok()
+            .and_then(|level_handle| ldtk_levels.get(level_handle)) else {
+            continue;
+        };
+
+        world_state.broken_cells
+            .entry(ldtk_level.level.iid.clone())
+            .or_default()
+            .insert((broken.grid_coords.x, broken.grid_coords.y));
+    }
+}
+
+/// Breaks the cells again as soon as their level comes back, the merged colliders
+/// get rebuilt around them like after any other break.
+fn apply_broken_blocks(
+    mut commands: Commands,
+    world_state: Res<WorldState>,
+    cell_query: Query<(Entity, &GridCoords, &Parent), Added<Breakable>>,
+    layer_query: Query<&Parent, With<LayerMetadata>>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>
+) {
+    for (entity, grid_coords, parent) in &cell_query {
+        let Some(ldtk_level) = layer_query
+            .get(parent.get())
+            .ok()
+            .and_then(|layer_parent| level_query.get(layer_parent.get()).ok())
+            .and_then(|level_handle| ldtk_levels.get(level_handle)) else {
+            continue;
+        };
+
+        if world_state.is_broken(&ldtk_level.level.iid, *grid_coords) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// A fresh run starts from a clean world, unless it's a save being loaded.
+fn reset_world_state(
+    mut restarts: EventReader<RestartWorld>,
+    pending_load: Res<PendingLoad>,
+    mut world_state: ResMut<WorldState>
+) {
+    if restarts.is_empty() {
+        return;
+    }
+    restarts.clear();
+
+    *world_state = pending_load.save
+        .as_ref()
+        .map(|save| save.world.clone())
+        .unwrap_or_default();
+}